@@ -1,6 +1,10 @@
+use std::fmt::{self, Display};
+
 use easy_collections::{map, EasyMap};
 use lazy_static::lazy_static;
 
+use crate::parse::{CallResult, StraceToken};
+
 lazy_static! {
     pub static ref FN_MAP: EasyMap<&'static str, FnInfo> = map! {
         ("access", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
@@ -13,77 +17,104 @@ lazy_static! {
         ("creat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
         ("execv", FnInfo::new(AccessMode::Read, ErrorReturnCode::Unknown)),
         ("execve", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("execveat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("faccessat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("fanotify_mark", FnInfo::new(AccessMode::Unknown, ErrorReturnCode::NEGATIVE)),
-        ("fchmodat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
-        ("fchownat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
-        ("fstat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("fstat64", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("fstatat64", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("fstatfs", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("fstatfs64", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("futimesat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
+        ("execveat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
+        ("faccessat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
+        ("fanotify_mark", FnInfo::new(AccessMode::Unknown, ErrorReturnCode::NEGATIVE).with_dirfd_arg(4, 3)),
+        ("fchmod", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("fchmodat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
+        ("fchown", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("fchown32", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("fchownat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
+        ("fdatasync", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("fgetxattr", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("flistxattr", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("fremovexattr", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("fsetxattr", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("fstat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("fstat64", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("fstatat64", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
+        ("fstatfs", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("fstatfs64", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("fsync", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("ftruncate", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("ftruncate64", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("futimesat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
         // NOTE: `man 2 getcwd` says it returns `NULL` on error, but `strace` interprets this as `-1`
         ("getcwd", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
+        ("getdents", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("getdents64", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
         ("getxattr", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
         ("inotify_add_watch", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
         ("link", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
-        ("linkat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
+        ("linkat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0).with_dirfd_arg(3, 2)),
         ("listxattr", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
         ("lstat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
         ("lstat64", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
         ("mkdir", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
-        ("mkdirat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
+        ("mkdirat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
         ("mknod", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
-        ("mknodat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
+        ("mknodat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
+        // NOTE: shared mappings with `PROT_WRITE` also write to the file, but these are rare enough to not distinguish
+        ("mmap", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(4)),
+        ("mmap2", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(4)),
         ("mount", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("name_to_handle_at", FnInfo::new(AccessMode::Unknown, ErrorReturnCode::NEGATIVE)),
-        ("newfstatat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("oldfstat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
+        ("name_to_handle_at", FnInfo::new(AccessMode::Unknown, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
+        ("newfstatat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
+        ("oldfstat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
         ("oldlstat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
         ("oldstat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("open", FnInfo::new(AccessMode::ReadWrite, ErrorReturnCode::NEGATIVE)),
-        ("openat", FnInfo::new(AccessMode::ReadWrite, ErrorReturnCode::NEGATIVE)),
+        ("open", FnInfo::new(AccessMode::ReadWrite, ErrorReturnCode::NEGATIVE).with_flags_arg(1)),
+        ("openat", FnInfo::new(AccessMode::ReadWrite, ErrorReturnCode::NEGATIVE).with_flags_arg(2).with_dirfd_arg(1, 0)),
+        ("openat2", FnInfo::new(AccessMode::ReadWrite, ErrorReturnCode::NEGATIVE).with_flags_arg(2).with_dirfd_arg(1, 0)),
         ("osf_fstatfs", FnInfo::new(AccessMode::Read, ErrorReturnCode::Unknown)),
         ("osf_statfs", FnInfo::new(AccessMode::Read, ErrorReturnCode::Unknown)),
         ("osf_utimes", FnInfo::new(AccessMode::Read, ErrorReturnCode::Unknown)),
         ("perror", FnInfo::new(AccessMode::Ignore, ErrorReturnCode::Unknown)),
         ("pivotroot", FnInfo::new(AccessMode::Read, ErrorReturnCode::Unknown)),
+        ("pread64", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("preadv", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("preadv2", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
         ("printargs", FnInfo::new(AccessMode::Unknown, ErrorReturnCode::Unknown)),
         ("printf", FnInfo::new(AccessMode::Ignore, ErrorReturnCode::Unknown)),
+        ("pwrite64", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("pwritev", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("pwritev2", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
         ("quotactl", FnInfo::new(AccessMode::Unknown, ErrorReturnCode::NEGATIVE)),
-        ("readlink", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("readlinkat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
+        ("read", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("readlink", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_link_args(1, 0)),
+        ("readlinkat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_link_args(2, 1).with_dirfd_arg(1, 0)),
+        ("readv", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
         ("removexattr", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
         ("rename", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
-        ("renameat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
-        ("renameat2", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
+        ("renameat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0).with_dirfd_arg(3, 2)),
+        ("renameat2", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0).with_dirfd_arg(3, 2)),
         ("rmdir", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
         ("setxattr", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
         ("stat", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
         ("stat64", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
         ("statfs", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
         ("statfs64", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
-        ("statx", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
+        ("statx", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
         ("swapoff", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
         ("swapon", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
-        ("symlink", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
-        ("symlinkat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
+        ("symlink", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_link_args(0, 1)),
+        ("symlinkat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_link_args(0, 2).with_dirfd_arg(2, 1)),
         ("truncate", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
         ("truncate64", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
         ("umount", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
         ("umount2", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
         ("unlink", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
-        ("unlinkat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
+        ("unlinkat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
         ("uselib", FnInfo::new(AccessMode::Read, ErrorReturnCode::NEGATIVE)),
         ("utime", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
-        ("utimensat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
+        ("utimensat", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_dirfd_arg(1, 0)),
         ("utimes", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE)),
+        ("write", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
+        ("writev", FnInfo::new(AccessMode::Write, ErrorReturnCode::NEGATIVE).with_fd_arg(0)),
     };
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AccessMode {
     Read,
     Write,
@@ -91,6 +122,63 @@ pub enum AccessMode {
     Ignore,
     Unknown,
 }
+
+impl AccessMode {
+    /// Determines the access mode from the flags passed to `open(2)` and friends.
+    /// The flags are either an expression (e.g.: `O_RDONLY|O_CLOEXEC`) or for `openat2(2)`, a `struct open_how`.
+    pub fn from_open_flags(token: &StraceToken) -> AccessMode {
+        let flags = match token {
+            StraceToken::Hash(items) => items
+                .iter()
+                .find(|(name, _)| *name == "flags")
+                .map(|(_, flags)| flags.idents())
+                .unwrap_or_default(),
+            token => token.idents(),
+        };
+
+        let mode = if flags.contains(&"O_RDWR") {
+            AccessMode::ReadWrite
+        } else if flags.contains(&"O_WRONLY") {
+            AccessMode::Write
+        } else if flags.contains(&"O_RDONLY") {
+            AccessMode::Read
+        } else {
+            AccessMode::Unknown
+        };
+
+        // these flags modify the file even if it was only opened for reading
+        let modifies = ["O_CREAT", "O_TRUNC", "O_APPEND", "O_TMPFILE"];
+        if flags.iter().any(|flag| modifies.contains(flag)) {
+            match mode {
+                AccessMode::Read => AccessMode::ReadWrite,
+                AccessMode::Unknown => AccessMode::Write,
+                mode => mode,
+            }
+        } else {
+            mode
+        }
+    }
+
+    pub fn is_read(&self) -> bool {
+        matches!(self, AccessMode::Read | AccessMode::ReadWrite)
+    }
+
+    pub fn is_write(&self) -> bool {
+        matches!(self, AccessMode::Write | AccessMode::ReadWrite)
+    }
+}
+
+impl Display for AccessMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            AccessMode::Read => "r",
+            AccessMode::Write => "w",
+            AccessMode::ReadWrite => "rw",
+            AccessMode::Ignore | AccessMode::Unknown => "?",
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ErrorReturnCode {
     Single(i32),
@@ -105,17 +193,73 @@ impl ErrorReturnCode {
 pub struct FnInfo {
     pub mode: AccessMode,
     pub err_code: ErrorReturnCode,
+    /// The index of the argument containing `open(2)` style flags, which determine the real access mode
+    pub flags_arg: Option<usize>,
+    /// The indices of the arguments containing a symlink's target and the symlink itself: the target is relative to
+    /// the directory containing the symlink rather than the working directory
+    pub link_args: Option<(usize, usize)>,
+    /// Pairs of argument indices of a path and the directory file descriptor that it's relative to
+    pub dirfd_args: Vec<(usize, usize)>,
+    /// The index of the argument containing a file descriptor of the file being accessed
+    pub fd_arg: Option<usize>,
 }
 
 impl FnInfo {
     pub fn new(mode: AccessMode, err_code: ErrorReturnCode) -> FnInfo {
-        FnInfo { mode, err_code }
+        FnInfo {
+            mode,
+            err_code,
+            flags_arg: None,
+            link_args: None,
+            dirfd_args: vec![],
+            fd_arg: None,
+        }
     }
 
-    pub fn did_succeed(&self, code: i32) -> Option<bool> {
-        match self.err_code {
-            ErrorReturnCode::Single(n) => Some(code != n),
-            ErrorReturnCode::Unknown => None,
+    pub fn with_flags_arg(mut self, index: usize) -> FnInfo {
+        self.flags_arg = Some(index);
+        self
+    }
+
+    pub fn with_link_args(mut self, target: usize, link: usize) -> FnInfo {
+        self.link_args = Some((target, link));
+        self
+    }
+
+    pub fn with_dirfd_arg(mut self, path: usize, dirfd: usize) -> FnInfo {
+        self.dirfd_args.push((path, dirfd));
+        self
+    }
+
+    pub fn with_fd_arg(mut self, index: usize) -> FnInfo {
+        self.fd_arg = Some(index);
+        self
+    }
+
+    /// Returns the index of the directory file descriptor argument that the path at `index` is relative to.
+    pub fn dirfd_arg(&self, index: usize) -> Option<usize> {
+        self.dirfd_args
+            .iter()
+            .find(|(path, _)| *path == index)
+            .map(|(_, dirfd)| *dirfd)
+    }
+
+    /// Returns the access mode of a call, taking into account any flags that were passed to it.
+    pub fn access_mode(&self, args: &[StraceToken]) -> AccessMode {
+        match self.flags_arg.and_then(|i| args.get(i)) {
+            Some(flags) => AccessMode::from_open_flags(flags),
+            None => self.mode,
+        }
+    }
+
+    /// Returns whether a call succeeded, if that can be determined from its result.
+    pub fn did_succeed(&self, result: &CallResult) -> Option<bool> {
+        match (result, &self.err_code) {
+            (CallResult::Error(_), _) => Some(false),
+            (CallResult::Unknown, _) | (_, ErrorReturnCode::Unknown) => None,
+            (CallResult::Int(n), ErrorReturnCode::Single(code)) => Some(*n != *code as i64),
+            // NOTE: values are only printed in hexadecimal when they're not errors (e.g.: addresses)
+            (CallResult::Hex(_), ErrorReturnCode::Single(_)) => Some(true),
         }
     }
 }
@@ -125,6 +269,91 @@ impl Default for FnInfo {
         FnInfo {
             mode: AccessMode::Unknown,
             err_code: ErrorReturnCode::Unknown,
+            flags_arg: None,
+            link_args: None,
+            dirfd_args: vec![],
+            fd_arg: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccessMode, FN_MAP};
+    use crate::parse::{StraceLine, StraceToken};
+
+    fn did_succeed(line: &str) -> Option<bool> {
+        match StraceLine::from_str(line).unwrap().inner {
+            StraceToken::Call {
+                name,
+                result: Some(result),
+                ..
+            } => FN_MAP[name].did_succeed(&result),
+            _ => unreachable!(),
+        }
+    }
+
+    fn mode(line: &str) -> AccessMode {
+        match StraceLine::from_str(line).unwrap().inner {
+            StraceToken::Call { name, args, .. } => FN_MAP[name].access_mode(&args),
+            _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn open_flags() {
+        assert_eq!(
+            mode(r#"openat(AT_FDCWD, "/etc/ld.so.cache", O_RDONLY|O_CLOEXEC) = 3"#),
+            AccessMode::Read
+        );
+        assert_eq!(
+            mode(r#"open("/tmp/out", O_WRONLY|O_CREAT|O_TRUNC, 0666) = 3"#),
+            AccessMode::Write
+        );
+        assert_eq!(
+            mode(r#"openat(AT_FDCWD, "/tmp/out", O_RDWR) = 3"#),
+            AccessMode::ReadWrite
+        );
+        assert_eq!(
+            mode(r#"openat(AT_FDCWD, "/tmp/out", O_RDONLY|O_CREAT, 0644) = 3"#),
+            AccessMode::ReadWrite
+        );
+        assert_eq!(
+            mode(
+                r#"openat2(AT_FDCWD, "/tmp/out", {flags=O_WRONLY|O_APPEND, mode=0, resolve=0}, 24) = 3"#
+            ),
+            AccessMode::Write
+        );
+    }
+
+    #[test]
+    fn static_modes() {
+        assert_eq!(
+            mode(r#"stat("/", {st_mode=S_IFDIR|0755}) = 0"#),
+            AccessMode::Read
+        );
+        assert_eq!(mode(r#"unlink("/tmp/out") = 0"#), AccessMode::Write);
+        assert_eq!(mode(r#"flock(3, LOCK_EX) = 0"#), AccessMode::Unknown);
+    }
+
+    #[test]
+    fn results() {
+        assert_eq!(
+            did_succeed(r#"openat(AT_FDCWD, "/etc/passwd", O_RDONLY) = 3"#),
+            Some(true)
+        );
+        assert_eq!(
+            did_succeed(r#"stat("/missing", 0x7ffc) = -1 ENOENT (No such file or directory)"#),
+            Some(false)
+        );
+        assert_eq!(
+            did_succeed(r#"mmap(NULL, 6, PROT_READ, MAP_PRIVATE, 3, 0) = 0x7f1c2a4b5000"#),
+            Some(true)
+        );
+        assert_eq!(
+            did_succeed(r#"execve("/bin/true", ["true"], 0x7ffc) = ?"#),
+            None
+        );
+        assert_eq!(did_succeed(r#"flock(3, LOCK_EX) = 0"#), None);
+    }
 }
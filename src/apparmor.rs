@@ -0,0 +1,346 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use ftrace::filter::ExePattern;
+use ftrace::FileAccess;
+
+/// The number of files in a directory with the same permissions that are collapsed into a single globbed rule.
+const GLOB_THRESHOLD: usize = 3;
+
+/// How a program that was executed runs, as the `x` permission of its rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Exec {
+    /// The program inherits the profile (`ix`)
+    Inherit,
+    /// The program transitions to its own profile (`px`)
+    Profile,
+}
+
+/// The permissions of a single rule of a profile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+struct Permissions {
+    read: bool,
+    write: bool,
+    /// Whether the file is mapped into memory as executable code, which is assumed for shared libraries
+    mmap: bool,
+    exec: Option<Exec>,
+}
+
+impl Permissions {
+    fn to_bytes(self) -> Vec<u8> {
+        let mut s = String::new();
+        for (set, c) in [(self.read, "r"), (self.write, "w"), (self.mmap, "m")] {
+            if set {
+                s.push_str(c);
+            }
+        }
+        match self.exec {
+            Some(Exec::Inherit) => s.push_str("ix"),
+            Some(Exec::Profile) => s.push_str("px"),
+            None => {}
+        }
+
+        s.into_bytes()
+    }
+}
+
+/// The files that a program accessed and executed, as they're output with `--format apparmor`.
+#[derive(Debug, Default)]
+pub struct Profile {
+    /// The program that was traced, which the profile is attached to
+    program: Option<PathBuf>,
+    /// The command line of the traced program
+    argv: Vec<OsString>,
+    /// The permissions of each escaped path (directories end with a `/`)
+    rules: HashMap<Vec<u8>, Permissions>,
+}
+
+impl Profile {
+    /// Adds a path that was accessed, ignoring any access that failed.
+    pub fn add(&mut self, access: &FileAccess) {
+        // programs are added by `Profile::exec`, since reading them isn't needed to execute them
+        if access.success != Some(true) || access.syscall.starts_with("execve") {
+            return;
+        }
+
+        let mut path = rule_path(&access.path);
+        if access.path.is_dir() && !path.ends_with(b"/") {
+            path.push(b'/');
+        }
+        let permissions = self.rules.entry(path).or_default();
+        permissions.read |= access.mode.is_read();
+        permissions.write |= access.mode.is_write();
+        permissions.mmap |= is_library(&access.path);
+    }
+
+    /// Adds a program that was executed, which is either the traced program itself or a program that it ran.
+    pub fn exec(&mut self, path: &Path, argv: &[OsString], transitions: &[ExePattern]) {
+        if self.program.is_none() {
+            self.program = Some(path.to_path_buf());
+            self.argv = argv.to_vec();
+            return;
+        }
+        if self.program.as_deref() == Some(path) {
+            return;
+        }
+
+        let exec = if transitions.iter().any(|pattern| pattern.matches(path)) {
+            Exec::Profile
+        } else {
+            Exec::Inherit
+        };
+        self.rules.entry(rule_path(path)).or_default().exec = Some(exec);
+    }
+
+    /// Returns the rules of the profile, with files in the same directory that have the same permissions collapsed
+    /// into a glob (e.g.: `/usr/share/zoneinfo/* r,`). Directories and programs are never collapsed.
+    fn rules(&self) -> BTreeMap<Vec<u8>, Permissions> {
+        let mut siblings = HashMap::<_, Vec<&[u8]>>::new();
+        for (path, permissions) in &self.rules {
+            if path.ends_with(b"/") || permissions.exec.is_some() {
+                continue;
+            }
+            if let Some(i) = path.iter().rposition(|b| *b == b'/') {
+                let key = (&path[..=i], *permissions);
+                siblings.entry(key).or_default().push(&path[i + 1..]);
+            }
+        }
+
+        let mut rules = self.rules.clone().into_iter().collect::<BTreeMap<_, _>>();
+        for ((dir, permissions), names) in siblings {
+            if names.len() < GLOB_THRESHOLD {
+                continue;
+            }
+
+            for name in &names {
+                rules.remove(&[dir, name].concat());
+            }
+            let mut glob = [dir, b"*"].concat();
+            if let Some(extension) = common_extension(&names) {
+                glob.extend(extension);
+            }
+            rules.insert(glob, permissions);
+        }
+
+        rules
+    }
+
+    /// Writes an AppArmor profile skeleton, which is meant to be reviewed and tightened before it's loaded.
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        if !self.argv.is_empty() {
+            let argv = self.argv.iter().map(|arg| arg.to_string_lossy());
+            writeln!(
+                w,
+                "# Generated by ftrace from: {}",
+                argv.collect::<Vec<_>>().join(" ")
+            )?;
+        }
+        writeln!(w, "#include <tunables/global>")?;
+        writeln!(w)?;
+
+        match &self.program {
+            Some(program) => {
+                let name = program.file_name().unwrap_or_else(|| program.as_os_str());
+                w.write_all(b"profile ")?;
+                w.write_all(&quote(&escape(name.as_bytes())))?;
+                w.write_all(b" ")?;
+                w.write_all(&quote(&rule_path(program)))?;
+                w.write_all(b" {\n")?;
+            }
+            None => writeln!(w, "profile unknown {{")?,
+        }
+        writeln!(w, "  #include <abstractions/base>")?;
+        writeln!(w)?;
+
+        for (path, permissions) in self.rules() {
+            w.write_all(b"  ")?;
+            w.write_all(&quote(&path))?;
+            w.write_all(b" ")?;
+            w.write_all(&permissions.to_bytes())?;
+            w.write_all(b",\n")?;
+        }
+
+        writeln!(w, "}}")
+    }
+}
+
+/// Returns the (escaped) path used in a rule, replacing the pid in paths such as `/proc/1234/maps` with `@{pid}`.
+fn rule_path(path: &Path) -> Vec<u8> {
+    let path = escape(path.as_os_str().as_bytes());
+    let pid = path.strip_prefix(b"/proc/").and_then(|rest| {
+        let end = rest.iter().position(|b| *b == b'/').unwrap_or(rest.len());
+        if end > 0 && rest[..end].iter().all(u8::is_ascii_digit) {
+            Some(&rest[end..])
+        } else {
+            None
+        }
+    });
+
+    match pid {
+        Some(rest) => [b"/proc/@{pid}", rest].concat(),
+        None => path,
+    }
+}
+
+/// Whether a file is a shared library, which is mapped into memory rather than only read.
+fn is_library(path: &Path) -> bool {
+    let mut name = match path.file_name() {
+        Some(name) => name.as_bytes(),
+        None => return false,
+    };
+    // skip any version, e.g.: `libc.so.6` or `libssl.so.1.1`
+    while let Some(i) = name.iter().rposition(|b| *b == b'.') {
+        if i + 1 == name.len() || !name[i + 1..].iter().all(u8::is_ascii_digit) {
+            break;
+        }
+        name = &name[..i];
+    }
+
+    name.ends_with(b".so")
+}
+
+/// Returns the extension (including the `.`) shared by all of the file names, if there is one.
+fn common_extension<'a>(names: &[&'a [u8]]) -> Option<&'a [u8]> {
+    let extension = |name: &'a [u8]| name.iter().rposition(|b| *b == b'.').map(|i| &name[i..]);
+    let first = extension(names.first()?)?;
+    if names.iter().all(|name| extension(name) == Some(first)) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+/// Escapes any characters that AppArmor treats as globs or variables.
+fn escape(path: &[u8]) -> Vec<u8> {
+    let mut escaped = vec![];
+    for b in path {
+        match b {
+            b'*' | b'?' | b'[' | b']' | b'{' | b'}' | b'^' | b'\\' | b'"' | b'@' => {
+                escaped.extend(&[b'\\', *b])
+            }
+            b => escaped.push(*b),
+        }
+    }
+
+    escaped
+}
+
+/// Quotes a path (or name) that has already been escaped, if it contains whitespace or other separators.
+fn quote(path: &[u8]) -> Vec<u8> {
+    if path.iter().any(|b| b.is_ascii_whitespace() || *b == b',') {
+        [b"\"", path, b"\""].concat()
+    } else {
+        path.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    use super::{
+        common_extension, escape, is_library, quote, rule_path, Exec, Permissions, Profile,
+    };
+
+    const READ: Permissions = Permissions {
+        read: true,
+        write: false,
+        mmap: false,
+        exec: None,
+    };
+
+    #[test]
+    fn collapse_rules() {
+        let write = Permissions {
+            write: true,
+            ..READ
+        };
+        let exec = Permissions {
+            exec: Some(Exec::Inherit),
+            ..Permissions::default()
+        };
+        let mut profile = Profile::default();
+        for path in ["/zone/a.tz", "/zone/b.tz", "/zone/c.tz"] {
+            profile.rules.insert(path.into(), READ);
+        }
+        for path in ["/etc/a", "/etc/b.conf", "/etc/c.conf"] {
+            profile.rules.insert(path.into(), READ);
+        }
+        // too few files with the same permissions
+        for (path, permissions) in [("/src/a.c", READ), ("/src/b.c", READ), ("/src/c.c", write)] {
+            profile.rules.insert(path.into(), permissions);
+        }
+        // directories and programs are never collapsed
+        for path in ["/bin/a", "/bin/b", "/bin/c"] {
+            profile.rules.insert(path.into(), exec);
+        }
+        for path in ["/data/a/", "/data/b/", "/data/c/"] {
+            profile.rules.insert(path.into(), READ);
+        }
+
+        let expected = [
+            ("/bin/a", exec),
+            ("/bin/b", exec),
+            ("/bin/c", exec),
+            ("/data/a/", READ),
+            ("/data/b/", READ),
+            ("/data/c/", READ),
+            ("/etc/*", READ),
+            ("/src/a.c", READ),
+            ("/src/b.c", READ),
+            ("/src/c.c", write),
+            ("/zone/*.tz", READ),
+        ];
+        assert_eq!(
+            profile.rules(),
+            expected
+                .iter()
+                .map(|(path, permissions)| (path.as_bytes().to_vec(), *permissions))
+                .collect::<BTreeMap<_, _>>()
+        );
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(
+            common_extension(&[b"a.tz", b"b.tz", b"c.d.tz"]),
+            Some(&b".tz"[..])
+        );
+        assert_eq!(common_extension(&[b"a.tz", b"b.tz", b"c.txt"]), None);
+        assert_eq!(common_extension(&[b"a", b"b", b"c"]), None);
+        assert_eq!(common_extension(&[]), None);
+    }
+
+    #[test]
+    fn libraries() {
+        assert!(is_library(Path::new("/lib/libc.so.6")));
+        assert!(is_library(Path::new("/lib/libssl.so.1.1")));
+        assert!(is_library(Path::new("/lib/libz.so")));
+        assert!(!is_library(Path::new("/lib/libz.so.")));
+        assert!(!is_library(Path::new("/lib/libz.so.x")));
+        assert!(!is_library(Path::new("/lib/libz.a")));
+        assert!(!is_library(Path::new("/")));
+    }
+
+    #[test]
+    fn escape_paths() {
+        assert_eq!(escape(b"/usr/bin/ls"), b"/usr/bin/ls");
+        assert_eq!(
+            escape(br#"/a*b?[c]{d}^"@\"#),
+            br#"/a\*b\?\[c\]\{d\}\^\"\@\\"#
+        );
+        assert_eq!(quote(b"/a b"), b"\"/a b\"");
+        assert_eq!(quote(b"/a,b"), b"\"/a,b\"");
+        assert_eq!(quote(b"/a\\*b"), b"/a\\*b");
+        assert_eq!(
+            rule_path(Path::new("/proc/1234/maps")),
+            b"/proc/@{pid}/maps"
+        );
+        assert_eq!(rule_path(Path::new("/proc/1234")), b"/proc/@{pid}");
+        assert_eq!(rule_path(Path::new("/proc/self/maps")), b"/proc/self/maps");
+    }
+}
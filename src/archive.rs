@@ -0,0 +1,254 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+
+use tar::{Builder, EntryType, Header, HeaderMode};
+
+use ftrace::errno::Errno;
+use ftrace::parse::CallResult;
+use ftrace::policy::interpreter;
+use ftrace::FileAccess;
+
+use crate::output::Quote;
+
+/// The path of the manifest of missing paths within the archive.
+pub const MISSING_MANIFEST: &str = ".ftrace/missing";
+
+/// The maximum number of symlinks that are followed when resolving a path, the same as Linux's limit.
+const MAX_SYMLINKS: usize = 40;
+
+/// Pseudo filesystems, whose files aren't archived since their contents are generated when they're read.
+const PSEUDO_FILESYSTEMS: &[&str] = &["/proc", "/sys", "/dev"];
+
+/// The files that a program read and the paths it probed that didn't exist, as they're written with `--archive`.
+#[derive(Debug, Default)]
+pub struct Archive {
+    /// Paths that were successfully read
+    read: BTreeSet<PathBuf>,
+    /// Paths that were successfully accessed in any way, which are never listed as missing
+    found: HashSet<PathBuf>,
+    /// Paths that were accessed but didn't exist
+    missing: BTreeSet<PathBuf>,
+}
+
+impl Archive {
+    /// Adds a path that was accessed.
+    pub fn add(&mut self, access: &FileAccess) {
+        match (access.success, access.result) {
+            (Some(true), _) => {
+                if access.mode.is_read() {
+                    self.read.insert(access.path.clone());
+                }
+                self.found.insert(access.path.clone());
+            }
+            (Some(false), CallResult::Error(Errno(libc::ENOENT))) => {
+                self.missing.insert(access.path.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /// Adds a program that was executed, along with the interpreter that the kernel runs it with (if any), since the
+    /// kernel reads the interpreter itself rather than the program opening it.
+    pub fn exec(&mut self, path: &Path) {
+        if let Some(interpreter) = interpreter(path) {
+            if self.read.insert(interpreter.clone()) {
+                self.exec(&interpreter);
+            }
+        }
+    }
+
+    /// Writes a tarball with every regular file that was read (at the path it resolves to), the symlinks that were
+    /// followed to reach each of them, and a manifest of the paths that were missing.
+    ///
+    /// Entries are written in order without ownership or timestamps, so tracing the same run twice gives the same
+    /// archive.
+    pub fn write(&self, w: impl Write) -> io::Result<()> {
+        let mut files = BTreeSet::new();
+        let mut links = BTreeSet::new();
+        for path in &self.read {
+            if is_pseudo(path) {
+                continue;
+            }
+            let mut chain = BTreeSet::new();
+            let path = match resolve(path, &mut chain, &mut 0) {
+                Some(path) if !is_pseudo(&path) => path,
+                _ => continue,
+            };
+            if fs::metadata(&path)
+                .map(|meta| meta.is_file())
+                .unwrap_or(false)
+            {
+                files.insert(path);
+                links.append(&mut chain);
+            }
+        }
+
+        let mut builder = Builder::new(w);
+        for path in links.iter().chain(&files) {
+            if let Err(e) = append(&mut builder, path) {
+                log::warn!("failed to archive {}: {}", path.display(), e);
+            }
+        }
+
+        let mut manifest = vec![];
+        for path in self
+            .missing
+            .iter()
+            .filter(|path| !self.found.contains(*path))
+        {
+            manifest.extend(Quote::C.quote(path));
+            manifest.push(b'\n');
+        }
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Regular);
+        header.set_mode(0o644);
+        header.set_size(manifest.len() as u64);
+        builder.append_data(&mut header, MISSING_MANIFEST, manifest.as_slice())?;
+
+        builder.into_inner()?.flush()
+    }
+}
+
+/// Appends a regular file or symlink to an archive with its mode, but without its ownership or timestamps.
+fn append(builder: &mut Builder<impl Write>, path: &Path) -> io::Result<()> {
+    let meta = path.symlink_metadata()?;
+    let mut header = Header::new_gnu();
+    header.set_metadata_in_mode(&meta, HeaderMode::Deterministic);
+    // NOTE: the deterministic mode also normalises permissions to either 0644 or 0755, so restore them
+    header.set_mode(meta.permissions().mode() & 0o7777);
+
+    // paths are stored relative to the root, so the archive can be extracted anywhere (e.g.: into a chroot)
+    let name = path.strip_prefix("/").unwrap_or(path);
+    if meta.file_type().is_symlink() {
+        builder.append_link(&mut header, name, fs::read_link(path)?)
+    } else {
+        builder.append_data(&mut header, name, File::open(path)?)
+    }
+}
+
+/// Whether a path is in a pseudo filesystem such as `/proc`.
+fn is_pseudo(path: &Path) -> bool {
+    PSEUDO_FILESYSTEMS.iter().any(|dir| path.starts_with(dir))
+}
+
+/// Resolves every symlink in an absolute path (including those in its parents), adding each one that's followed to
+/// `links`, and returns the path that it refers to. Returns `None` if the path no longer exists.
+fn resolve(path: &Path, links: &mut BTreeSet<PathBuf>, followed: &mut usize) -> Option<PathBuf> {
+    let mut resolved = PathBuf::from("/");
+    for component in path.components() {
+        let name = match component {
+            Component::Normal(name) => name,
+            Component::ParentDir => {
+                resolved.pop();
+                continue;
+            }
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => continue,
+        };
+
+        let next = resolved.join(name);
+        if !next.symlink_metadata().ok()?.file_type().is_symlink() {
+            resolved = next;
+            continue;
+        }
+
+        *followed += 1;
+        if *followed > MAX_SYMLINKS {
+            return None;
+        }
+        // relative targets are relative to the directory that contains the link
+        let target = resolved.join(fs::read_link(&next).ok()?);
+        links.insert(next);
+        resolved = resolve(&target, links, followed)?;
+    }
+
+    Some(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use std::path::PathBuf;
+
+    use super::{is_pseudo, resolve, MAX_SYMLINKS};
+
+    #[test]
+    fn resolve_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("lib/x86_64")).unwrap();
+        fs::write(root.join("lib/x86_64/libz.so.1.2"), "").unwrap();
+        symlink("libz.so.1.2", root.join("lib/x86_64/libz.so.1")).unwrap();
+        symlink("lib/x86_64", root.join("lib64")).unwrap();
+
+        let mut links = BTreeSet::new();
+        assert_eq!(
+            resolve(&root.join("lib64/libz.so.1"), &mut links, &mut 0),
+            Some(root.join("lib/x86_64/libz.so.1.2"))
+        );
+        assert_eq!(
+            links,
+            vec![root.join("lib/x86_64/libz.so.1"), root.join("lib64")]
+                .into_iter()
+                .collect()
+        );
+
+        // `..` applies to the target of a symlink, in the same way as the kernel
+        let mut links = BTreeSet::new();
+        assert_eq!(
+            resolve(
+                &root.join("lib64/../x86_64/./libz.so.1.2"),
+                &mut links,
+                &mut 0
+            ),
+            Some(root.join("lib/x86_64/libz.so.1.2"))
+        );
+        assert_eq!(links.len(), 1);
+
+        assert_eq!(
+            resolve(&root.join("lib64/missing"), &mut BTreeSet::new(), &mut 0),
+            None
+        );
+    }
+
+    #[test]
+    fn resolve_symlink_loops() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        symlink("b", root.join("a")).unwrap();
+        symlink("a", root.join("b")).unwrap();
+        assert_eq!(resolve(&root.join("a"), &mut BTreeSet::new(), &mut 0), None);
+
+        // a chain of exactly the maximum number of symlinks is still followed
+        fs::write(root.join("file"), "").unwrap();
+        symlink("file", root.join("link0")).unwrap();
+        for i in 1..MAX_SYMLINKS {
+            symlink(format!("link{}", i - 1), root.join(format!("link{}", i))).unwrap();
+        }
+        let last = root.join(format!("link{}", MAX_SYMLINKS - 1));
+        let mut followed = 0;
+        assert_eq!(
+            resolve(&last, &mut BTreeSet::new(), &mut followed),
+            Some(root.join("file"))
+        );
+        assert_eq!(followed, MAX_SYMLINKS);
+
+        symlink(&last, root.join("too-many")).unwrap();
+        assert_eq!(
+            resolve(&root.join("too-many"), &mut BTreeSet::new(), &mut 0),
+            None
+        );
+    }
+
+    #[test]
+    fn pseudo_paths() {
+        assert!(is_pseudo(&PathBuf::from("/proc/self/maps")));
+        assert!(is_pseudo(&PathBuf::from("/dev/null")));
+        assert!(!is_pseudo(&PathBuf::from("/devices")));
+        assert!(!is_pseudo(&PathBuf::from("/etc/passwd")));
+    }
+}
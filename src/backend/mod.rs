@@ -0,0 +1,23 @@
+pub mod ptrace;
+pub mod strace;
+
+use clap::ArgEnum;
+
+/// The mechanism used to trace the program's syscalls.
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+pub enum Backend {
+    /// Trace natively via `ptrace(2)`
+    Ptrace,
+    /// Spawn `strace` and parse its output
+    Strace,
+}
+
+impl Backend {
+    /// Prefers `strace` when it's installed, and falls back to the native backend when it isn't.
+    pub fn detect() -> Backend {
+        match strace::find() {
+            Ok(_) => Backend::Strace,
+            Err(_) => Backend::Ptrace,
+        }
+    }
+}
@@ -0,0 +1,538 @@
+//! A native tracer built directly on top of `ptrace(2)`.
+//!
+//! Rather than scraping text, this reads syscall arguments straight out of the tracee's memory and emits the same
+//! `StraceLine`s that the `strace` backend would produce, so the rest of the program doesn't need to know which backend
+//! is in use. It requires `PTRACE_GET_SYSCALL_INFO`, which was added in Linux 5.3, and only has syscall tables for
+//! `x86_64`, `aarch64` and `riscv64`.
+
+mod syscalls;
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::ptr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fs, io, mem};
+
+use anyhow::{anyhow, Context, Result};
+use libc::{c_int, c_long, c_void, pid_t};
+use which::which;
+
+use crate::errno::Errno;
+use crate::landlock::Ruleset;
+use crate::parse::{CallInfo, CallResult, StraceLine, StraceToken};
+use crate::signal;
+use syscalls::{Arg, Syscall};
+
+/// The program that attach messages are attributed to, where `strace` would print its own path.
+const PROGRAM: &str = env!("CARGO_PKG_NAME");
+/// The longest string that will be read from the tracee's memory.
+const MAX_STRING_LEN: usize = libc::PATH_MAX as usize;
+/// The most `argv` entries that will be read from the tracee's memory.
+const MAX_ARGV_LEN: usize = 4096;
+
+const OPTIONS: c_int = libc::PTRACE_O_TRACESYSGOOD
+    | libc::PTRACE_O_TRACEFORK
+    | libc::PTRACE_O_TRACEVFORK
+    | libc::PTRACE_O_TRACECLONE
+    | libc::PTRACE_O_TRACEEXEC;
+
+/// A decoded syscall argument which owns its data, so that a `StraceToken` can borrow from it.
+#[derive(Debug)]
+enum Value {
+    Str(String),
+    Array(Vec<String>),
+    Hash(Vec<(&'static str, Value)>),
+    Flags(Vec<&'static str>, u64),
+    Ident(&'static str),
+    Num(i64),
+    Null,
+}
+
+impl Value {
+    fn token(&self) -> StraceToken<'_> {
+        match self {
+            Value::Str(s) => StraceToken::String(s),
+            Value::Array(items) => {
+                StraceToken::Array(items.iter().map(|s| StraceToken::String(s)).collect())
+            }
+            Value::Hash(items) => StraceToken::Hash(
+                items
+                    .iter()
+                    .map(|(name, value)| (*name, value.token()))
+                    .collect(),
+            ),
+            Value::Flags(names, remaining) => {
+                let mut items = names
+                    .iter()
+                    .map(|name| StraceToken::Ident(name))
+                    .collect::<Vec<_>>();
+                if *remaining != 0 || items.is_empty() {
+                    items.push(StraceToken::Number(*remaining as i64));
+                }
+
+                // join them all with `|` operators
+                let mut expr = vec![];
+                for (i, item) in items.into_iter().enumerate() {
+                    if i > 0 {
+                        expr.push(StraceToken::Op("|"));
+                    }
+                    expr.push(item);
+                }
+                StraceToken::Expr(expr)
+            }
+            Value::Ident(ident) => StraceToken::Expr(vec![StraceToken::Ident(ident)]),
+            Value::Num(n) => StraceToken::Expr(vec![StraceToken::Number(*n)]),
+            Value::Null => StraceToken::Null,
+        }
+    }
+}
+
+/// A syscall that has been entered, but hasn't yet exited.
+#[derive(Debug)]
+struct PendingCall {
+    syscall: &'static Syscall,
+    raw_args: [u64; 6],
+    args: Vec<Value>,
+}
+
+#[derive(Debug, Default)]
+struct Tracee {
+    /// Whether the initial `SIGSTOP` has been received and the ptrace options have been set
+    initialised: bool,
+    pending: Option<PendingCall>,
+}
+
+struct Tracer<F> {
+    options: c_int,
+    tracees: HashMap<pid_t, Tracee>,
+    f: F,
+}
+
+/// Traces either the given command or an existing process, calling `f` with each line.
+/// If `f` returns `false` then tracing stops. The command is restricted to the `sandbox` ruleset, if one is given.
+pub fn trace<F>(cmd: &[String], pid: Option<usize>, sandbox: Option<&Ruleset>, f: F) -> Result<()>
+where
+    F: FnMut(&StraceLine) -> bool,
+{
+    if !syscalls::SUPPORTED {
+        return Err(anyhow!(
+            "The ptrace backend doesn't support the {} architecture, install strace and use `--backend strace`",
+            std::env::consts::ARCH
+        ));
+    }
+
+    let mut tracer = Tracer {
+        options: OPTIONS,
+        tracees: HashMap::new(),
+        f,
+    };
+
+    match pid {
+        Some(pid) => {
+            if !tracer.attach(pid as pid_t)? {
+                return Ok(());
+            }
+        }
+        None => {
+            let pid = spawn(cmd, sandbox)?;
+            // the spawned process should never outlive us
+            tracer.options |= libc::PTRACE_O_EXITKILL;
+            tracer.tracees.insert(pid, Tracee::default());
+        }
+    }
+
+    tracer.run()
+}
+
+/// Forks and execs the given command, requesting to be traced before the command is executed.
+fn spawn(cmd: &[String], sandbox: Option<&Ruleset>) -> Result<pid_t> {
+    let path = which(&cmd[0]).with_context(|| format!("Failed to find command: {}", cmd[0]))?;
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let args = cmd
+        .iter()
+        .map(|arg| CString::new(arg.as_bytes()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut argv = args.iter().map(|arg| arg.as_ptr()).collect::<Vec<_>>();
+    argv.push(ptr::null());
+
+    // NOTE: only async-signal-safe functions may be used in the child, so everything is prepared before forking
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()).context("Failed to fork"),
+        0 => unsafe {
+            let _ = ptrace(libc::PTRACE_TRACEME, 0, 0, 0);
+            if let Some(sandbox) = sandbox {
+                if sandbox.restrict_self().is_err() {
+                    let msg = b"ftrace: failed to apply the Landlock sandbox\n";
+                    libc::write(
+                        libc::STDERR_FILENO,
+                        msg.as_ptr() as *const c_void,
+                        msg.len(),
+                    );
+                    libc::_exit(126);
+                }
+            }
+            // stop so the tracer can set its options before the command is executed
+            libc::raise(libc::SIGSTOP);
+            libc::execv(path.as_ptr(), argv.as_ptr());
+            libc::_exit(127);
+        },
+        pid => Ok(pid),
+    }
+}
+
+impl<F> Tracer<F>
+where
+    F: FnMut(&StraceLine) -> bool,
+{
+    fn emit(&mut self, pid: Option<pid_t>, inner: StraceToken) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        (self.f)(&StraceLine {
+            pid,
+            time: Some(Duration::from_micros(now.as_micros() as u64)),
+            inner,
+        })
+    }
+
+    /// Attaches to every thread of an existing process.
+    /// Returns `false` if permission to attach was denied.
+    fn attach(&mut self, pid: pid_t) -> Result<bool> {
+        let tasks = fs::read_dir(format!("/proc/{}/task", pid))
+            .with_context(|| format!("Failed to find process: {}", pid))?;
+        for task in tasks {
+            let tid = match task?.file_name().to_string_lossy().parse::<pid_t>() {
+                Ok(tid) => tid,
+                Err(_) => continue,
+            };
+
+            if let Err(e) = ptrace(libc::PTRACE_ATTACH, tid, 0, 0) {
+                match e.raw_os_error() {
+                    // the thread exited before we could attach to it
+                    Some(libc::ESRCH) => continue,
+                    Some(libc::EPERM) => {
+                        self.emit(None, StraceToken::PermissionDenied(PROGRAM, pid));
+                        return Ok(false);
+                    }
+                    _ => return Err(e).context("Failed to attach to process"),
+                }
+            }
+
+            self.tracees.insert(tid, Tracee::default());
+            if !self.emit(None, StraceToken::ProcessAttach(PROGRAM, tid)) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn run(&mut self) -> Result<()> {
+        while !self.tracees.is_empty() {
+            let mut status = 0;
+            let pid = unsafe { libc::waitpid(-1, &mut status, libc::__WALL) };
+            if pid == -1 {
+                let e = io::Error::last_os_error();
+                match e.raw_os_error() {
+                    Some(libc::EINTR) => continue,
+                    Some(libc::ECHILD) => break,
+                    _ => return Err(e).context("Failed to wait for tracee"),
+                }
+            }
+
+            log::trace!("waitpid: pid={} status={:#x}", pid, status);
+            if libc::WIFEXITED(status) {
+                self.tracees.remove(&pid);
+                if !self.emit(Some(pid), StraceToken::Exit(libc::WEXITSTATUS(status))) {
+                    break;
+                }
+                continue;
+            }
+
+            if libc::WIFSIGNALED(status) {
+                self.tracees.remove(&pid);
+                let name = signal::name(libc::WTERMSIG(status));
+                if !self.emit(Some(pid), StraceToken::Kill(&name)) {
+                    break;
+                }
+                continue;
+            }
+
+            if !libc::WIFSTOPPED(status) {
+                continue;
+            }
+
+            let mut inject = 0;
+            let sig = libc::WSTOPSIG(status);
+            let event = status >> 16;
+            let keep_going = if sig == libc::SIGTRAP | 0x80 {
+                self.syscall_stop(pid)?
+            } else if event != 0 {
+                self.event_stop(pid, event)?
+            } else if !self.tracees.entry(pid).or_default().initialised && sig == libc::SIGSTOP {
+                // the first stop of a new tracee: newly forked children are automatically traced, but we may see their
+                // initial stop before the event from their parent
+                ptrace(libc::PTRACE_SETOPTIONS, pid, 0, self.options as u64)?;
+                self.tracees.entry(pid).or_default().initialised = true;
+                true
+            } else {
+                // if there's no signal information then this is a group-stop, and the signal must not be re-injected
+                let mut info: libc::siginfo_t = unsafe { mem::zeroed() };
+                if ptrace(libc::PTRACE_GETSIGINFO, pid, 0, &mut info as *mut _ as u64).is_ok() {
+                    inject = sig;
+                    let name = signal::name(sig);
+                    self.emit(Some(pid), StraceToken::Signal(&name, vec![]))
+                } else {
+                    true
+                }
+            };
+
+            if !keep_going {
+                break;
+            }
+
+            // the tracee may have exited while it was stopped (e.g.: a `SIGKILL` from elsewhere)
+            if let Err(e) = ptrace(libc::PTRACE_SYSCALL, pid, 0, inject as u64) {
+                log::debug!("failed to resume {}: {}", pid, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn event_stop(&mut self, pid: pid_t, event: c_int) -> Result<bool> {
+        let mut msg: c_long = 0;
+        ptrace(libc::PTRACE_GETEVENTMSG, pid, 0, &mut msg as *mut _ as u64)?;
+        match event {
+            libc::PTRACE_EVENT_FORK | libc::PTRACE_EVENT_VFORK | libc::PTRACE_EVENT_CLONE => {
+                let child = msg as pid_t;
+                self.tracees.entry(child).or_default();
+                Ok(self.emit(None, StraceToken::ProcessAttach(PROGRAM, child)))
+            }
+            libc::PTRACE_EVENT_EXEC => {
+                // if a thread other than the thread group leader called `execve` then the kernel changes its pid to
+                // that of the leader, so move its pending call across
+                let former = msg as pid_t;
+                if former != pid {
+                    if let Some(tracee) = self.tracees.remove(&former) {
+                        self.tracees.entry(pid).or_default().pending = tracee.pending;
+                    }
+                }
+                Ok(true)
+            }
+            _ => Ok(true),
+        }
+    }
+
+    fn syscall_stop(&mut self, pid: pid_t) -> Result<bool> {
+        let mut info: libc::ptrace_syscall_info = unsafe { mem::zeroed() };
+        ptrace(
+            libc::PTRACE_GET_SYSCALL_INFO,
+            pid,
+            mem::size_of::<libc::ptrace_syscall_info>() as u64,
+            &mut info as *mut _ as u64,
+        )
+        .map_err(|e| anyhow!("{} (the ptrace backend requires Linux 5.3 or newer)", e))?;
+
+        match info.op {
+            libc::PTRACE_SYSCALL_INFO_ENTRY => {
+                let entry = unsafe { info.u.entry };
+                if let Some(syscall) = syscalls::lookup(entry.nr) {
+                    let args = syscall
+                        .args
+                        .iter()
+                        .zip(entry.args.iter())
+                        .map(|(arg, raw)| match arg {
+                            Arg::DirFd if *raw as c_int == libc::AT_FDCWD => {
+                                Value::Ident("AT_FDCWD")
+                            }
+                            Arg::DirFd | Arg::Fd | Arg::Int => Value::Num(*raw as c_int as i64),
+                            Arg::Path => match read_string(pid, *raw) {
+                                Some(bytes) => Value::Str(escape(&bytes)),
+                                None => Value::Null,
+                            },
+                            Arg::Argv => Value::Array(
+                                read_argv(pid, *raw)
+                                    .iter()
+                                    .map(|bytes| escape(bytes))
+                                    .collect(),
+                            ),
+                            Arg::OpenFlags => {
+                                let (names, remaining) = syscalls::open_flags(*raw);
+                                Value::Flags(names, remaining)
+                            }
+                            // the first field of `struct open_how` is the `open(2)` flags
+                            Arg::OpenHow => match read_u64(pid, *raw) {
+                                Some(flags) => {
+                                    let (names, remaining) = syscalls::open_flags(flags);
+                                    Value::Hash(vec![("flags", Value::Flags(names, remaining))])
+                                }
+                                None => Value::Num(*raw as i64),
+                            },
+                            // the first field of `struct clone_args` is the `clone(2)` flags
+                            Arg::CloneArgs => match read_u64(pid, *raw) {
+                                Some(flags) => {
+                                    let (names, remaining) =
+                                        syscalls::flags(syscalls::CLONE_FLAGS, flags);
+                                    Value::Hash(vec![("flags", Value::Flags(names, remaining))])
+                                }
+                                None => Value::Num(*raw as i64),
+                            },
+                            Arg::Flags(names) => {
+                                let (names, remaining) = syscalls::flags(names, *raw);
+                                Value::Flags(names, remaining)
+                            }
+                            Arg::Const(names) => {
+                                match names.iter().find(|(value, _)| *value as u64 == *raw) {
+                                    Some((_, name)) => Value::Ident(name),
+                                    None => Value::Num(*raw as c_int as i64),
+                                }
+                            }
+                            Arg::OutStr | Arg::Ptr if *raw == 0 => Value::Null,
+                            Arg::OutStr | Arg::Ptr => Value::Num(*raw as i64),
+                        })
+                        .collect();
+
+                    self.tracees.entry(pid).or_default().pending = Some(PendingCall {
+                        syscall,
+                        raw_args: entry.args,
+                        args,
+                    });
+                }
+
+                Ok(true)
+            }
+            libc::PTRACE_SYSCALL_INFO_EXIT => {
+                let exit = unsafe { info.u.exit };
+                let mut call = match self.tracees.get_mut(&pid).and_then(|t| t.pending.take()) {
+                    Some(call) => call,
+                    None => return Ok(true),
+                };
+
+                let (result, info) = if exit.is_error != 0 {
+                    let errno = Errno(-exit.sval as i32);
+                    (CallResult::Error(errno), Some(errno.message()))
+                } else {
+                    // now that the call has completed, read any strings the kernel has written
+                    for (i, arg) in call.syscall.args.iter().enumerate() {
+                        if *arg == Arg::OutStr {
+                            let len = (exit.sval as usize).min(MAX_STRING_LEN);
+                            if let Some(mut bytes) = read_bytes(pid, call.raw_args[i], len) {
+                                if bytes.last() == Some(&0) {
+                                    bytes.pop();
+                                }
+                                call.args[i] = Value::Str(escape(&bytes));
+                            }
+                        }
+                    }
+                    (CallResult::Int(exit.sval), None)
+                };
+
+                Ok(self.emit(
+                    Some(pid),
+                    StraceToken::Call {
+                        name: call.syscall.name(),
+                        args: call.args.iter().map(Value::token).collect(),
+                        result: Some(result),
+                        info: info.as_deref().map(|comment| CallInfo {
+                            comment: Some(comment),
+                            duration: None,
+                        }),
+                    },
+                ))
+            }
+            _ => Ok(true),
+        }
+    }
+}
+
+fn ptrace(request: libc::c_uint, pid: pid_t, addr: u64, data: u64) -> io::Result<c_long> {
+    match unsafe { libc::ptrace(request, pid, addr as *mut c_void, data as *mut c_void) } {
+        -1 => Err(io::Error::last_os_error()),
+        n => Ok(n),
+    }
+}
+
+/// Escapes bytes in the same way as `strace --strings-in-hex`, so they may be decoded with `decode_hex`.
+fn escape(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("\\x{:02x}", b)).collect()
+}
+
+/// Reads up to `len` bytes from the tracee's memory, stopping early if an unreadable page is reached.
+fn read_bytes(pid: pid_t, addr: u64, len: usize) -> Option<Vec<u8>> {
+    if addr == 0 {
+        return None;
+    }
+
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+    let mut bytes = vec![0u8; len];
+    let mut read = 0;
+    while read < len {
+        // never read across a page boundary, since the next page may not be mapped
+        let addr = addr + read as u64;
+        let chunk = ((page_size - addr % page_size) as usize).min(len - read);
+        let local = libc::iovec {
+            iov_base: bytes[read..].as_mut_ptr() as *mut c_void,
+            iov_len: chunk,
+        };
+        let remote = libc::iovec {
+            iov_base: addr as *mut c_void,
+            iov_len: chunk,
+        };
+        match unsafe { libc::process_vm_readv(pid, &local, 1, &remote, 1, 0) } {
+            n if n <= 0 => break,
+            n => read += n as usize,
+        }
+    }
+
+    bytes.truncate(read);
+    Some(bytes)
+}
+
+/// Reads a single `u64` from the tracee's memory.
+fn read_u64(pid: pid_t, addr: u64) -> Option<u64> {
+    let bytes = read_bytes(pid, addr, mem::size_of::<u64>())?;
+    let mut buf = [0u8; mem::size_of::<u64>()];
+    if bytes.len() != buf.len() {
+        return None;
+    }
+
+    buf.copy_from_slice(&bytes);
+    Some(u64::from_ne_bytes(buf))
+}
+
+/// Reads a NUL-terminated string from the tracee's memory.
+fn read_string(pid: pid_t, addr: u64) -> Option<Vec<u8>> {
+    let mut bytes = read_bytes(pid, addr, MAX_STRING_LEN)?;
+    if let Some(end) = bytes.iter().position(|b| *b == 0) {
+        bytes.truncate(end);
+    }
+
+    Some(bytes)
+}
+
+/// Reads a NULL-terminated array of strings from the tracee's memory.
+fn read_argv(pid: pid_t, addr: u64) -> Vec<Vec<u8>> {
+    const PTR_SIZE: usize = mem::size_of::<usize>();
+
+    let mut argv = vec![];
+    while argv.len() < MAX_ARGV_LEN {
+        let ptr_addr = addr + (argv.len() * PTR_SIZE) as u64;
+        let ptr = match read_bytes(pid, ptr_addr, PTR_SIZE) {
+            Some(bytes) if bytes.len() == PTR_SIZE => {
+                let mut buf = [0u8; PTR_SIZE];
+                buf.copy_from_slice(&bytes);
+                usize::from_ne_bytes(buf) as u64
+            }
+            _ => break,
+        };
+
+        match read_string(pid, ptr) {
+            Some(arg) => argv.push(arg),
+            None => break,
+        }
+    }
+
+    argv
+}
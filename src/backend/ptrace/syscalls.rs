@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use libc::{c_int, c_long};
+
+use Arg::*;
+
+/// Describes how a syscall argument should be decoded from the tracee.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arg {
+    /// A directory file descriptor, which may be `AT_FDCWD`
+    DirFd,
+    /// A regular file descriptor
+    Fd,
+    /// A pointer to a NUL-terminated path, read when the syscall is entered
+    Path,
+    /// A pointer to a buffer that the kernel fills with a string, read when the syscall exits
+    OutStr,
+    /// A pointer to a NULL-terminated array of string pointers (e.g.: `argv` for `execve`)
+    Argv,
+    /// The flags passed to `open(2)`
+    OpenFlags,
+    /// A pointer to the `struct open_how` passed to `openat2(2)`
+    OpenHow,
+    /// A pointer to the `struct clone_args` passed to `clone3(2)`
+    CloneArgs,
+    /// A bit set of flags, decoded with the given names
+    Flags(&'static [(c_int, &'static str)]),
+    /// One of a set of constants, decoded with the given names
+    Const(&'static [(c_int, &'static str)]),
+    /// Any other integer value
+    Int,
+    /// Any other pointer value
+    Ptr,
+}
+
+#[derive(Debug)]
+pub struct Syscall {
+    pub nr: c_long,
+    pub args: &'static [Arg],
+    constant: &'static str,
+}
+
+impl Syscall {
+    /// The name of the syscall, without the `SYS_` prefix of its constant.
+    pub fn name(&self) -> &'static str {
+        &self.constant[4..]
+    }
+}
+
+macro_rules! syscall {
+    ($constant:ident, [$( $arg:expr ),*]) => {
+        Syscall {
+            nr: libc::$constant,
+            args: &[$( $arg ),*],
+            constant: stringify!($constant),
+        }
+    };
+}
+
+/// Whether there's a syscall table for the architecture that was compiled for. The tables below assume the 64-bit
+/// syscall ABI, so 32-bit architectures (which have e.g. `fstatat64` rather than `newfstatat`) aren't supported.
+pub const SUPPORTED: bool = cfg!(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "riscv64"
+));
+
+/// The syscalls that make up `strace`'s `%file` class that are available on every supported architecture, along with
+/// the syscalls that change the working directory or create processes, and those that access a file through its file
+/// descriptor (the same syscalls that the `strace` backend traces).
+#[cfg(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "riscv64"
+))]
+const SYSCALLS: &[Syscall] = &[
+    syscall!(SYS_acct, [Path]),
+    syscall!(SYS_chdir, [Path]),
+    syscall!(SYS_chroot, [Path]),
+    syscall!(SYS_clone, [Flags(CLONE_FLAGS), Ptr, Ptr, Ptr, Ptr]),
+    syscall!(SYS_clone3, [CloneArgs, Int]),
+    syscall!(SYS_close, [Fd]),
+    syscall!(SYS_close_range, [Fd, Fd, Flags(CLOSE_RANGE_FLAGS)]),
+    syscall!(SYS_dup, [Fd]),
+    syscall!(SYS_dup3, [Fd, Fd, Flags(DUP3_FLAGS)]),
+    syscall!(SYS_execve, [Path, Argv, Ptr]),
+    syscall!(SYS_execveat, [DirFd, Path, Argv, Ptr, Int]),
+    syscall!(SYS_faccessat, [DirFd, Path, Int]),
+    syscall!(SYS_faccessat2, [DirFd, Path, Int, Int]),
+    syscall!(SYS_fchdir, [Fd]),
+    syscall!(SYS_fchmod, [Fd, Int]),
+    syscall!(SYS_fanotify_mark, [Fd, Int, Int, DirFd, Path]),
+    syscall!(SYS_fchmodat, [DirFd, Path, Int]),
+    syscall!(SYS_fchown, [Fd, Int, Int]),
+    syscall!(SYS_fchownat, [DirFd, Path, Int, Int, Int]),
+    syscall!(SYS_fcntl, [Fd, Const(FCNTL_CMDS), Int]),
+    syscall!(SYS_fstat, [Fd, Ptr]),
+    syscall!(SYS_fstatfs, [Fd, Ptr]),
+    syscall!(SYS_ftruncate, [Fd, Int]),
+    syscall!(SYS_getcwd, [OutStr, Int]),
+    syscall!(SYS_getdents64, [Fd, Ptr, Int]),
+    syscall!(SYS_getxattr, [Path, Ptr, Ptr, Int]),
+    syscall!(SYS_inotify_add_watch, [Fd, Path, Int]),
+    syscall!(SYS_lgetxattr, [Path, Ptr, Ptr, Int]),
+    syscall!(SYS_linkat, [DirFd, Path, DirFd, Path, Int]),
+    syscall!(SYS_listxattr, [Path, Ptr, Int]),
+    syscall!(SYS_llistxattr, [Path, Ptr, Int]),
+    syscall!(SYS_lremovexattr, [Path, Ptr]),
+    syscall!(SYS_lsetxattr, [Path, Ptr, Ptr, Int, Int]),
+    syscall!(SYS_mkdirat, [DirFd, Path, Int]),
+    syscall!(SYS_mknodat, [DirFd, Path, Int, Int]),
+    syscall!(SYS_mmap, [Ptr, Int, Int, Int, Fd, Int]),
+    syscall!(SYS_mount, [Path, Path, Ptr, Int, Ptr]),
+    syscall!(SYS_name_to_handle_at, [DirFd, Path, Ptr, Ptr, Int]),
+    syscall!(SYS_newfstatat, [DirFd, Path, Ptr, Int]),
+    syscall!(SYS_openat, [DirFd, Path, OpenFlags, Int]),
+    syscall!(SYS_openat2, [DirFd, Path, OpenHow, Int]),
+    syscall!(SYS_pivot_root, [Path, Path]),
+    syscall!(SYS_pread64, [Fd, Ptr, Int, Int]),
+    syscall!(SYS_pwrite64, [Fd, Ptr, Int, Int]),
+    syscall!(SYS_quotactl, [Int, Path, Int, Ptr]),
+    syscall!(SYS_read, [Fd, Ptr, Int]),
+    syscall!(SYS_readlinkat, [DirFd, Path, OutStr, Int]),
+    syscall!(SYS_readv, [Fd, Ptr, Int]),
+    syscall!(SYS_removexattr, [Path, Ptr]),
+    syscall!(SYS_renameat2, [DirFd, Path, DirFd, Path, Int]),
+    syscall!(SYS_setxattr, [Path, Ptr, Ptr, Int, Int]),
+    syscall!(SYS_statfs, [Path, Ptr]),
+    syscall!(SYS_statx, [DirFd, Path, Int, Int, Ptr]),
+    syscall!(SYS_swapoff, [Path]),
+    syscall!(SYS_swapon, [Path, Int]),
+    syscall!(SYS_symlinkat, [Path, DirFd, Path]),
+    syscall!(SYS_truncate, [Path, Int]),
+    syscall!(SYS_umount2, [Path, Int]),
+    syscall!(SYS_unlinkat, [DirFd, Path, Int]),
+    syscall!(SYS_utimensat, [DirFd, Path, Ptr, Int]),
+    syscall!(SYS_write, [Fd, Ptr, Int]),
+    syscall!(SYS_writev, [Fd, Ptr, Int]),
+];
+
+/// Older syscalls that newer architectures (such as `aarch64`) have dropped in favour of their `*at` variants.
+#[cfg(target_arch = "x86_64")]
+const LEGACY_SYSCALLS: &[Syscall] = &[
+    syscall!(SYS_access, [Path, Int]),
+    syscall!(SYS_chmod, [Path, Int]),
+    syscall!(SYS_chown, [Path, Int, Int]),
+    syscall!(SYS_creat, [Path, Int]),
+    syscall!(SYS_dup2, [Fd, Fd]),
+    syscall!(SYS_fork, []),
+    syscall!(SYS_futimesat, [DirFd, Path, Ptr]),
+    syscall!(SYS_getdents, [Fd, Ptr, Int]),
+    syscall!(SYS_lchown, [Path, Int, Int]),
+    syscall!(SYS_link, [Path, Path]),
+    syscall!(SYS_lstat, [Path, Ptr]),
+    syscall!(SYS_mkdir, [Path, Int]),
+    syscall!(SYS_mknod, [Path, Int, Int]),
+    syscall!(SYS_open, [Path, OpenFlags, Int]),
+    syscall!(SYS_readlink, [Path, OutStr, Int]),
+    syscall!(SYS_rename, [Path, Path]),
+    syscall!(SYS_renameat, [DirFd, Path, DirFd, Path]),
+    syscall!(SYS_rmdir, [Path]),
+    syscall!(SYS_stat, [Path, Ptr]),
+    syscall!(SYS_symlink, [Path, Path]),
+    syscall!(SYS_unlink, [Path]),
+    syscall!(SYS_uselib, [Path]),
+    syscall!(SYS_utime, [Path, Ptr]),
+    syscall!(SYS_utimes, [Path, Ptr]),
+    syscall!(SYS_vfork, []),
+];
+
+/// Architectures that only have the generic syscall table don't have any of the legacy syscalls.
+#[cfg(any(target_arch = "aarch64", target_arch = "riscv64"))]
+const LEGACY_SYSCALLS: &[Syscall] = &[];
+
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "riscv64"
+)))]
+const SYSCALLS: &[Syscall] = &[];
+
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "riscv64"
+)))]
+const LEGACY_SYSCALLS: &[Syscall] = &[];
+
+/// Flags for `open(2)`, ordered so that flags which are a superset of others are matched first.
+const OPEN_FLAGS: &[(c_int, &str)] = &[
+    (libc::O_TMPFILE, "O_TMPFILE"),
+    (libc::O_SYNC, "O_SYNC"),
+    (libc::O_CREAT, "O_CREAT"),
+    (libc::O_EXCL, "O_EXCL"),
+    (libc::O_NOCTTY, "O_NOCTTY"),
+    (libc::O_TRUNC, "O_TRUNC"),
+    (libc::O_APPEND, "O_APPEND"),
+    (libc::O_NONBLOCK, "O_NONBLOCK"),
+    (libc::O_DSYNC, "O_DSYNC"),
+    (libc::O_ASYNC, "O_ASYNC"),
+    (libc::O_DIRECT, "O_DIRECT"),
+    (libc::O_DIRECTORY, "O_DIRECTORY"),
+    (libc::O_NOFOLLOW, "O_NOFOLLOW"),
+    (libc::O_NOATIME, "O_NOATIME"),
+    (libc::O_CLOEXEC, "O_CLOEXEC"),
+    (libc::O_PATH, "O_PATH"),
+];
+
+/// Flags for `clone(2)`; the lowest byte is the signal sent to the parent when the child exits.
+pub const CLONE_FLAGS: &[(c_int, &str)] = &[
+    (libc::CLONE_VM, "CLONE_VM"),
+    (libc::CLONE_FS, "CLONE_FS"),
+    (libc::CLONE_FILES, "CLONE_FILES"),
+    (libc::CLONE_SIGHAND, "CLONE_SIGHAND"),
+    (libc::CLONE_PIDFD, "CLONE_PIDFD"),
+    (libc::CLONE_PTRACE, "CLONE_PTRACE"),
+    (libc::CLONE_VFORK, "CLONE_VFORK"),
+    (libc::CLONE_PARENT, "CLONE_PARENT"),
+    (libc::CLONE_THREAD, "CLONE_THREAD"),
+    (libc::CLONE_NEWNS, "CLONE_NEWNS"),
+    (libc::CLONE_SYSVSEM, "CLONE_SYSVSEM"),
+    (libc::CLONE_SETTLS, "CLONE_SETTLS"),
+    (libc::CLONE_PARENT_SETTID, "CLONE_PARENT_SETTID"),
+    (libc::CLONE_CHILD_CLEARTID, "CLONE_CHILD_CLEARTID"),
+    (libc::CLONE_CHILD_SETTID, "CLONE_CHILD_SETTID"),
+];
+
+/// Flags for `close_range(2)`.
+const CLOSE_RANGE_FLAGS: &[(c_int, &str)] = &[
+    (libc::CLOSE_RANGE_UNSHARE as c_int, "CLOSE_RANGE_UNSHARE"),
+    (libc::CLOSE_RANGE_CLOEXEC as c_int, "CLOSE_RANGE_CLOEXEC"),
+];
+
+/// Flags for `dup3(2)`.
+const DUP3_FLAGS: &[(c_int, &str)] = &[(libc::O_CLOEXEC, "O_CLOEXEC")];
+
+/// Commands for `fcntl(2)` which affect the file descriptor table.
+const FCNTL_CMDS: &[(c_int, &str)] = &[
+    (libc::F_DUPFD, "F_DUPFD"),
+    (libc::F_DUPFD_CLOEXEC, "F_DUPFD_CLOEXEC"),
+    (libc::F_GETFD, "F_GETFD"),
+    (libc::F_SETFD, "F_SETFD"),
+];
+
+/// Decodes a bit set of flags using the given names.
+/// Returns the names of the flags, and any remaining bits which couldn't be decoded.
+pub fn flags(names: &[(c_int, &'static str)], value: u64) -> (Vec<&'static str>, u64) {
+    let mut decoded = vec![];
+    let mut remaining = value;
+    for (flag, name) in names {
+        let flag = *flag as u64;
+        if remaining & flag == flag {
+            decoded.push(*name);
+            remaining &= !flag;
+        }
+    }
+
+    (decoded, remaining)
+}
+
+/// Decodes `open(2)` flags into their names in the same way as `strace`.
+/// Returns the names of the flags, and any remaining bits which couldn't be decoded.
+pub fn open_flags(flags: u64) -> (Vec<&'static str>, u64) {
+    let mut names = vec![match flags as c_int & libc::O_ACCMODE {
+        libc::O_RDONLY => "O_RDONLY",
+        libc::O_WRONLY => "O_WRONLY",
+        libc::O_RDWR => "O_RDWR",
+        _ => "O_ACCMODE",
+    }];
+
+    let (decoded, remaining) = self::flags(OPEN_FLAGS, flags & !(libc::O_ACCMODE as u64));
+    names.extend(decoded);
+
+    (names, remaining)
+}
+
+lazy_static! {
+    static ref SYSCALL_MAP: HashMap<u64, &'static Syscall> = SYSCALLS
+        .iter()
+        .chain(LEGACY_SYSCALLS.iter())
+        .map(|syscall| (syscall.nr as u64, syscall))
+        .collect();
+}
+
+/// Returns information about the given syscall number, if it's a syscall that we're interested in.
+pub fn lookup(nr: u64) -> Option<&'static Syscall> {
+    SYSCALL_MAP.get(&nr).copied()
+}
@@ -0,0 +1,132 @@
+use std::ffi::CString;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+
+use anyhow::{Context, Result};
+use tempfile::{Builder, TempDir};
+use which::which;
+
+/// Finds the `strace` binary.
+pub fn find() -> which::Result<PathBuf> {
+    which("strace")
+}
+
+/// The options `strace` is spawned with, which traces passed to `--input` should also be recorded with.
+pub const OPTIONS: &[&str] = &[
+    // follow and trace the process's forks
+    "--follow-forks",
+    // monitor all statuses: even though this is almost the same as the default behaviour, by specifying this
+    // `strace` will usually wait for each syscall to end before printing it. Calls made by multiple threads at the
+    // same time may still be split into `<unfinished ...>` and `<... XXX resumed>` lines, which are joined later
+    "--status=successful,failed,unfinished,unavailable,detached",
+    // include timestamps with microsecond precision
+    "-ttt",
+    // print all strings with hexadecimal escapes
+    "--strings-in-hex",
+    // print the paths of file descriptors, so files that are only accessed by their file descriptor can be seen
+    "--decode-fds=all",
+    // only trace file syscalls since that's what we're interested in, along with the syscalls that change the
+    // working directory, create processes or manage file descriptors so relative paths can be resolved (`?`
+    // ignores syscalls that don't exist on the current architecture)
+    concat!(
+        "--trace=%file,fchdir,clone,?clone3,?fork,?vfork,close,?close_range,dup,?dup2,?dup3,fcntl,?fcntl64,",
+        "%fstat,%fstatfs,read,?pread64,readv,write,?pwrite64,writev,?mmap,?mmap2,fchmod,fchown,?fchown32,",
+        "ftruncate,getdents64,?getdents"
+    ),
+    // as from `man strace`: Use this option to get all of the gory details
+    "--no-abbrev",
+];
+
+/// A running `strace` process.
+pub struct Strace {
+    pub child: Child,
+    /// The trace written by `strace`, which is kept separate from anything the traced program writes
+    pub output: File,
+    /// Holds the FIFO open for writing until `strace` exits, since it may exit before it ever opens it
+    writer: File,
+    /// The directory the FIFO was created in, which is removed when this is dropped
+    dir: TempDir,
+}
+
+impl Strace {
+    /// Waits for `strace` to exit, and then closes the FIFO so reading `output` reaches its end.
+    pub fn wait(self) -> (File, JoinHandle<io::Result<ExitStatus>>) {
+        let Strace {
+            mut child,
+            output,
+            writer,
+            dir,
+        } = self;
+        let handle = thread::spawn(move || {
+            let status = child.wait();
+            drop(writer);
+            drop(dir);
+            status
+        });
+
+        (output, handle)
+    }
+}
+
+/// Creates a FIFO for `strace` to write its trace to, returning both ends of it.
+fn fifo(dir: &Path) -> Result<(PathBuf, File, File)> {
+    let path = dir.join("trace");
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } == -1 {
+        return Err(io::Error::last_os_error()).context("Failed to create FIFO");
+    }
+
+    // NOTE: opening either end of a FIFO blocks until the other end is opened, unless it's opened in non-blocking mode
+    let reader = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(&path)?;
+    let writer = OpenOptions::new().write(true).open(&path)?;
+    let flags = unsafe { libc::fcntl(reader.as_raw_fd(), libc::F_GETFL) };
+    if unsafe { libc::fcntl(reader.as_raw_fd(), libc::F_SETFL, flags & !libc::O_NONBLOCK) } == -1 {
+        return Err(io::Error::last_os_error()).context("Failed to configure FIFO");
+    }
+
+    Ok((path, reader, writer))
+}
+
+/// Spawns `strace` to trace either the given command or an existing process.
+/// `strace`'s output is written to a FIFO, and the command's STDIN, STDOUT and STDERR are passed through untouched.
+/// When attaching to a process `strace`'s STDERR is piped, so its messages (e.g.: "Process 42 attached") can be seen.
+pub fn spawn(strace_path: PathBuf, cmd: &[String], pid: Option<usize>) -> Result<Strace> {
+    let dir = Builder::new().prefix(".ftrace").tempdir()?;
+    let (fifo_path, output, writer) = fifo(dir.path())?;
+    let child = Command::new(strace_path)
+        // write the trace to the FIFO rather than STDERR, which the traced program may also write to
+        // NOTE: `strace` opens this with `O_CLOEXEC`, so the traced program doesn't inherit it
+        .arg("-o")
+        .arg(&fifo_path)
+        .args(OPTIONS)
+        // the user-provided command
+        .args(cmd)
+        // the user-provided pid
+        .args(
+            pid.map(|pid| vec![format!("--attach={}", pid)])
+                .unwrap_or_default(),
+        )
+        // `strace` still logs its own messages via stderr
+        .stderr(if pid.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        })
+        .spawn()?;
+
+    Ok(Strace {
+        child,
+        output,
+        writer,
+        dir,
+    })
+}
@@ -1,17 +1,21 @@
-use clap::ArgSettings::{AllowHyphenValues, Last};
+use std::env;
+use std::path::PathBuf;
+
+use clap::ArgSettings::Last;
 use clap::{crate_authors, crate_description, crate_name, crate_version};
-use clap::Parser;
-
-#[derive(Debug, Default)]
-pub struct FileTypes {
-    pub files: bool,
-    pub directories: bool,
-    pub symlinks: bool,
-    pub sockets: bool,
-    pub pipes: bool,
-    pub executables: bool,
-    pub empty: bool,
-}
+use clap::{AppSettings, ErrorKind, IntoApp, Parser, Subcommand};
+use globset::Glob;
+use lazy_static::lazy_static;
+use regex::bytes::Regex;
+
+use ftrace::backend::{strace, Backend};
+use ftrace::errno::Errno;
+use ftrace::filter::{AccessModes, ExcludeSet, ExePattern, FileTypes, PathFilter};
+
+use crate::config;
+use crate::diff::Normalize;
+use crate::manifest::HashAlgorithm;
+use crate::output::{Format, GroupBy, Quote};
 
 pub const POSSIBLE_TYPES: &[&str] = &[
     "f",
@@ -30,6 +34,17 @@ pub const POSSIBLE_TYPES: &[&str] = &[
     "pipe",
 ];
 
+pub const POSSIBLE_ACCESS_MODES: &[&str] = &[
+    "r",
+    "read",
+    "w",
+    "write",
+    "rw",
+    "read-write",
+    "?",
+    "unknown",
+];
+
 const AFTER_HELP: &str = concat!(
     "Note: `",
     crate_name!(),
@@ -38,12 +53,23 @@ const AFTER_HELP: &str = concat!(
     " --help` gives all details."
 );
 
+lazy_static! {
+    /// The long help for `--input`, which lists the options that `strace` is spawned with.
+    static ref INPUT_HELP: String = format!(
+        "Read existing `strace` output from a file (or `-` for STDIN) rather than tracing a program\n\n\
+        For best results, the output should be recorded with the same options that are used when `strace` is \
+        spawned, for example: `strace {} -o trace.log -- ls`",
+        strace::OPTIONS.join(" ")
+    );
+}
+
 #[derive(Debug, Parser)]
 #[clap(
     version = crate_version!(),
     about = crate_description!(),
     author = crate_authors!(),
     after_help = AFTER_HELP,
+    setting = AppSettings::SubcommandsNegateReqs,
 )]
 pub struct Args {
     /// Command to trace
@@ -51,20 +77,38 @@ pub struct Args {
     /// The specified command is executed directly and does not employ a shell, so scripts without shebang that usually
     /// run just fine when invoked by shell fail to execute. It is advisable to manually supply a shell as a command
     /// with the script as its argument
-    #[clap(setting = AllowHyphenValues, setting = Last, min_values = 1, required_unless_present = "pid")]
+    #[clap(setting = Last, min_values = 1, required_unless_present_any = &["pid", "input"])]
     pub cmd: Vec<String>,
     /// Optional PID to of running process to trace (note requires elevated privileges)
     #[clap(short = 'p', long = "pid")]
     pub pid: Option<usize>,
+    /// Read existing `strace` output from a file (or `-` for STDIN) rather than tracing a program
+    #[clap(
+        long = "input",
+        long_help = INPUT_HELP.as_str(),
+        conflicts_with_all = &["cmd", "pid", "backend"]
+    )]
+    pub input: Option<PathBuf>,
+    /// The working directory of the traced program when its output was recorded (only used with --input)
+    ///
+    /// Relative paths are resolved against this directory, which defaults to the current directory.
+    #[clap(long = "cwd", requires = "input")]
+    pub cwd: Option<PathBuf>,
+    /// Which backend to use to trace the program [default: strace if installed, otherwise ptrace]
+    ///
+    /// The `strace` backend spawns `strace` and parses its output, whereas the `ptrace` backend traces the program
+    /// natively and doesn't require `strace` to be installed (note the `ptrace` backend requires Linux 5.3 or newer
+    /// on `x86_64`, `aarch64` or `riscv64`).
+    #[clap(short = 'b', long = "backend", arg_enum)]
+    pub backend: Option<Backend>,
     /// Print output with terminal colors
     #[clap(short = 'c', long = "color")]
     pub color: bool,
     /// Print lines that the program failed to parse (see --help for more)
     ///
-    /// This program uses `strace` in order to trace system calls. One of the caveats is that `strace` outputs over
-    /// `STDERR`: this can be a problem if the program to be traced also outputs data on `STDERR` as the output may
-    /// confuse the parser.
-    /// This option will log all lines that are send over `STDERR` that failed to be correctly parsed.
+    /// Any line of `strace` output that isn't recognised is ignored, which can happen when reading a trace with
+    /// --input that was recorded with different options, or that contains the traced program's own output.
+    /// This option will log all lines that failed to be correctly parsed.
     #[clap(short = 'i', long = "invalid")]
     pub invalid_lines: bool,
     /// Print paths that the program attempted to access but didn't exist
@@ -92,21 +136,355 @@ pub struct Args {
     file_types: Vec<String>,
     #[clap(skip)]
     _file_types: Option<FileTypes>,
-    // TODO: filter by access pattern: r,w,rw,?,all
+    /// Filter the search by access mode (multiple allowable modes can be specified)
+    ///
+    /// The access mode is derived from the syscall, and for `open` and its variants, the flags passed to it.
+    /// Possible modes are:
+    ///     'r' or 'read':         paths that were read (including those opened for reading and writing)
+    ///     'w' or 'write':        paths that were written (including those opened for reading and writing)
+    ///     'rw' or 'read-write':  paths that were opened for both reading and writing
+    ///     '?' or 'unknown':      paths where the access mode couldn't be determined
+    #[clap(short = 'a', long = "access", verbatim_doc_comment, hide_possible_values = true, multiple_occurrences = true, possible_values = POSSIBLE_ACCESS_MODES)]
+    access_modes: Vec<String>,
+    #[clap(skip)]
+    _access_modes: Option<AccessModes>,
+    /// Only print paths that the program failed to access with one of these errors (e.g.: `ENOENT,EACCES`)
+    ///
+    /// Errors may be given by name or by number, and this implies --non-existent for the given errors.
+    #[clap(long = "errno", multiple_occurrences = true, use_delimiter = true)]
+    pub errnos: Vec<Errno>,
+    /// Only print paths that match this glob (multiple globs can be specified)
+    ///
+    /// Globs are matched against the absolute path, and `*` matches any characters including `/` (e.g.: `*.c` or
+    /// `/home/**`). Paths that match any --include glob or --regex are printed.
+    #[clap(long = "include", value_name = "GLOB", multiple_occurrences = true)]
+    pub include: Vec<Glob>,
+    /// Don't print paths that match this glob (multiple globs can be specified)
+    #[clap(long = "exclude", value_name = "GLOB", multiple_occurrences = true)]
+    pub exclude: Vec<Glob>,
+    /// Only print paths that match this regular expression (multiple expressions can be specified)
+    #[clap(long = "regex", value_name = "RE", multiple_occurrences = true)]
+    pub regexes: Vec<Regex>,
+    /// Don't print paths in a built-in set of paths
+    ///
+    /// Possible sets are:
+    ///     'system':  /proc, /sys, /dev, /usr/lib/locale and the dynamic loader's cache (/etc/ld.so.cache and
+    ///                /etc/ld.so.preload)
+    #[clap(
+        long = "exclude-set",
+        value_name = "SET",
+        verbatim_doc_comment,
+        hide_possible_values = true,
+        arg_enum,
+        multiple_occurrences = true
+    )]
+    pub exclude_sets: Vec<ExcludeSet>,
+    /// Only print paths accessed by processes running a program with this name, or that matches this glob
+    ///
+    /// Programs are matched by their file name (e.g.: `cc1` or `ld*`), or by their absolute path if the pattern
+    /// contains a `/` (e.g.: `/usr/lib/gcc/**`). The program of each process is known from its calls to `execve`.
+    #[clap(
+        long = "only-exe",
+        value_name = "NAME|GLOB",
+        multiple_occurrences = true
+    )]
+    pub only_exes: Vec<ExePattern>,
+    /// Only print paths accessed by the process with this pid
+    #[clap(long = "only-pid", value_name = "PID", multiple_occurrences = true)]
+    pub only_pids: Vec<i32>,
+    /// Don't print paths accessed by processes running a program with this name, or that matches this glob
+    #[clap(
+        long = "exclude-exe",
+        value_name = "NAME|GLOB",
+        multiple_occurrences = true
+    )]
+    pub exclude_exes: Vec<ExePattern>,
+    /// Also include (or exclude) the descendants of the processes matched by --only-exe, --only-pid or --exclude-exe
+    ///
+    /// Descendants continue to be matched even after they execute another program, e.g.: `--only-exe make
+    /// --descendants` prints the paths accessed by `make` and every program it runs.
+    #[clap(long = "descendants")]
+    pub descendants: bool,
+    /// Print the access mode of each path ('r', 'w', 'rw' or '?') before the path itself
+    #[clap(short = 'm', long = "show-mode")]
+    pub show_mode: bool,
+    /// Print each path unquoted on its own line, e.g.: for `rsync --files-from`
+    ///
+    /// This is the same as `--quote none`. Paths that contain a newline can't be told apart, so use --print0 if that's
+    /// a possibility.
+    #[clap(long = "plain", conflicts_with_all = &["print0", "show-mode", "group-by", "tree"])]
+    pub plain: bool,
+    /// Print the raw bytes of each path followed by a NUL byte rather than a newline, e.g.: for `xargs -0`
+    #[clap(short = '0', long = "print0", conflicts_with_all = &["show-mode", "group-by", "tree"])]
+    pub print0: bool,
+    /// How to quote each path printed in the `text` format
+    ///
+    /// `c` wraps paths in double quotes and escapes special characters like C, `shell` only quotes paths when they
+    /// need to be (so they can be pasted into a shell), `none` prints the raw bytes of each path, and `json` prints
+    /// them as JSON strings. Bytes that aren't valid UTF-8 are escaped as `\xNN` by `c` and `shell`, and replaced by
+    /// `json`.
+    #[clap(long = "quote", arg_enum, default_value = "c", value_name = "STYLE")]
+    pub quote: Quote,
+    /// The format to print each path in
+    ///
+    /// The `jsonl` format prints one JSON object per line, with fields such as the syscall, pid, timestamp and access
+    /// mode of each access (see the README for the schema).
+    /// The `depfile` format writes a Make-compatible `.d` file to --output once tracing has finished, whose
+    /// prerequisites are the regular files that were successfully read (see --target).
+    /// The `apparmor` format writes an AppArmor profile skeleton for the traced program to --output once tracing has
+    /// finished (see --transition).
+    #[clap(short = 'f', long = "format", arg_enum, default_value = "text")]
+    pub format: Format,
+    /// The target of the rule written by `--format depfile` (multiple targets can be specified)
+    #[clap(long = "target", value_name = "NAME", multiple_occurrences = true)]
+    pub targets: Vec<PathBuf>,
+    /// Also use the regular files that were written as targets of the rule written by `--format depfile`
+    #[clap(long = "written-targets")]
+    pub written_targets: bool,
+    /// The file to write the output of `--format depfile` or `--format apparmor` to
+    ///
+    /// This is required since the traced program's own output is printed to STDOUT, which would corrupt it.
+    #[clap(short = 'o', long = "output", value_name = "FILE")]
+    pub output: Option<PathBuf>,
+    /// Programs executed with this name (or that match this glob) transition to their own AppArmor profile (`px`)
+    ///
+    /// Other programs that are executed inherit the profile written by `--format apparmor` (`ix`). Programs are matched
+    /// in the same way as --only-exe.
+    #[clap(
+        long = "transition",
+        value_name = "NAME|GLOB",
+        multiple_occurrences = true
+    )]
+    pub transitions: Vec<ExePattern>,
+    /// Once tracing has finished, write the regular files that were successfully read to this tarball
+    ///
+    /// Files are stored at their absolute paths (relative to the root of the archive) with their modes, along with any
+    /// symlinks that were followed to reach them, so the archive can be extracted into a chroot to reproduce the run.
+    /// The archive also contains a `.ftrace/missing` manifest of the paths that were accessed but didn't exist.
+    /// Paths are archived after the filters are applied, and files in /proc, /sys and /dev are never archived.
+    #[clap(long = "archive", value_name = "FILE", conflicts_with = "relative-to")]
+    pub archive: Option<PathBuf>,
+    /// Write a manifest of the digest of every regular file that was successfully accessed to --manifest once tracing
+    /// has finished
+    ///
+    /// Files that were read are hashed when they're first seen, and files that were written are hashed once the
+    /// process that wrote them has exited. The manifest is in the same format as `sha256sum`, so it can be checked
+    /// with `sha256sum -c`.
+    #[clap(
+        long = "hash",
+        arg_enum,
+        value_name = "ALGORITHM",
+        requires = "manifest"
+    )]
+    pub hash: Option<HashAlgorithm>,
+    /// The file to write the manifest of --hash to
+    #[clap(long = "manifest", value_name = "FILE", requires = "hash")]
+    pub manifest: Option<PathBuf>,
+    /// Group the paths by the process that accessed them, printing each group once tracing has finished
+    ///
+    /// Each group starts with the pid of the process and the program it was running, which shows which program in a
+    /// build pipeline accessed each path. Programs that a process executed after its first are grouped separately.
+    #[clap(short = 'g', long = "group-by", arg_enum)]
+    pub group_by: Option<GroupBy>,
+    /// Print the tree of processes that were created once tracing has finished, rather than the paths themselves
+    ///
+    /// Each process is printed with the program it was running, how many paths it accessed (after filtering) and how
+    /// it exited.
+    #[clap(long = "tree")]
+    pub tree: bool,
+    /// Use the options of this profile from the config file (see the README for more)
+    ///
+    /// Options are read from `$XDG_CONFIG_HOME/ftrace/config.toml` and from the nearest `.ftrace.toml` in the current
+    /// directory or its parents. Profiles are defined in tables such as `[profile.build]`, and options given on the
+    /// command line take precedence over those from the config files.
+    #[clap(long = "profile", value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Print paths relative to this directory rather than as absolute paths
+    #[clap(long = "relative-to")]
+    pub relative_to: Option<PathBuf>,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Trace a command and save the paths that it accessed as a policy, which can be enforced with `ftrace enforce`
+    ///
+    /// Nothing is printed while recording. The options before `record` apply as usual, e.g.: `ftrace --input
+    /// trace.log record --policy tool.toml` records a policy from existing `strace` output, except that paths and
+    /// processes are never filtered (e.g.: by --type, --include or --relative-to) since the policy has to allow every
+    /// access that the program makes.
+    Record {
+        /// The file to save the policy to, as TOML
+        #[clap(long = "policy", value_name = "FILE")]
+        policy: PathBuf,
+        /// Command to trace
+        #[clap(setting = Last, min_values = 1)]
+        cmd: Vec<String>,
+    },
+    /// Run a command in a Landlock sandbox that only allows the accesses in a policy, printing any access that's denied
+    ///
+    /// Denied accesses fail with `EACCES`, so every access that fails with `EACCES` is printed (unless --errno is
+    /// given). This always uses the `ptrace` backend, and requires Linux 5.13 or newer with Landlock enabled.
+    Enforce {
+        /// The policy to enforce, as saved by `ftrace record`
+        #[clap(long = "policy", value_name = "FILE")]
+        policy: PathBuf,
+        /// Command to run
+        #[clap(setting = Last, min_values = 1, required = true)]
+        cmd: Vec<String>,
+    },
+    /// Compare two saved traces, printing the paths that were added (+), removed (-) or accessed differently (~)
+    ///
+    /// Each trace is either the output of `--format jsonl`, or the raw output of `strace` (see --input). A path is
+    /// accessed differently if its access mode changed, or if it was found in one trace but missing in the other.
+    /// Exits with 0 if the traces are the same, 1 if they differ and 2 if they couldn't be read. Paths are filtered by
+    /// --include, --exclude, --regex and --exclude-set.
+    Diff {
+        /// The trace to compare against, e.g.: from before a dependency was upgraded
+        #[clap(value_name = "A")]
+        a: PathBuf,
+        /// The trace to compare
+        #[clap(value_name = "B")]
+        b: PathBuf,
+        /// Replace parts of paths that differ between runs before comparing them (multiple can be given)
+        ///
+        /// `pids` replaces pids in /proc with `<pid>`, `timestamps` replaces dates, times and Unix timestamps with
+        /// `<timestamp>`, `temp-dirs` replaces random names beneath /tmp, /var/tmp, /dev/shm and $TMPDIR with `XXXXXX`,
+        /// and `all` does all of them.
+        #[clap(
+            long = "normalize",
+            value_name = "WHAT",
+            arg_enum,
+            multiple_occurrences = true,
+            use_delimiter = true
+        )]
+        normalize: Vec<Normalize>,
+    },
 }
 
 impl Args {
     pub fn parse() -> Args {
-        let mut args = <Args as Parser>::parse();
+        let args = match config::apply(env::args_os().collect()) {
+            Ok(args) => args,
+            Err(e) => Args::into_app()
+                .error(ErrorKind::InvalidValue, format!("{:#}", e))
+                .exit(),
+        };
+        let mut args = <Args as Parser>::parse_from(args);
         args._file_types = Args::parse_file_types(&args.file_types);
+        args._access_modes = Args::parse_access_modes(&args.access_modes);
+        let conflict = |msg: &str| {
+            Args::into_app()
+                .error(ErrorKind::ArgumentConflict, msg)
+                .exit()
+        };
+        if args.plain || args.print0 {
+            if args.format != Format::Text {
+                conflict("--plain and --print0 can only be used with --format text");
+            }
+            // anything other than the paths themselves would corrupt the output
+            args.quote = Quote::None;
+            args.color = false;
+        }
+        match args.format {
+            Format::Text => {}
+            // terminal colors would corrupt the output
+            Format::Jsonl | Format::Depfile | Format::Apparmor => {
+                args.color = false;
+                if args.group_by.is_some() || args.tree {
+                    conflict("--group-by and --tree can only be used with --format text");
+                }
+            }
+        }
+        match args.format {
+            Format::Depfile if args.targets.is_empty() && !args.written_targets => {
+                conflict("--format depfile requires --target or --written-targets")
+            }
+            Format::Depfile if args.output.is_none() => {
+                conflict("--format depfile requires --output")
+            }
+            Format::Apparmor if args.output.is_none() => {
+                conflict("--format apparmor requires --output")
+            }
+            // AppArmor rules must be absolute paths
+            Format::Apparmor if args.relative_to.is_some() => {
+                conflict("--format apparmor can't be used with --relative-to")
+            }
+            // only regular files belong in a depfile
+            Format::Depfile => {
+                args._file_types
+                    .get_or_insert_with(FileTypes::default)
+                    .files = true
+            }
+            _ if !args.targets.is_empty() || args.written_targets => {
+                conflict("--target and --written-targets can only be used with --format depfile")
+            }
+            _ => {}
+        }
+        if !matches!(args.format, Format::Depfile | Format::Apparmor) && args.output.is_some() {
+            conflict("--output can only be used with --format depfile or apparmor");
+        }
+        if args.format != Format::Apparmor && !args.transitions.is_empty() {
+            conflict("--transition can only be used with --format apparmor");
+        }
+        match &mut args.command {
+            Some(Command::Record { cmd, .. }) => {
+                if cmd.is_empty() && args.input.is_none() && args.pid.is_none() {
+                    conflict("record requires a command, --input or --pid");
+                }
+                if args.format != Format::Text || args.group_by.is_some() || args.tree {
+                    conflict("record can't be used with --format, --group-by or --tree");
+                }
+                args.cmd = std::mem::take(cmd);
+            }
+            Some(Command::Enforce { cmd, .. }) => {
+                if args.input.is_some() || args.pid.is_some() {
+                    conflict("enforce can't be used with --input or --pid");
+                }
+                if args.backend == Some(Backend::Strace) {
+                    conflict("enforce can only be used with the ptrace backend");
+                }
+                args.backend = Some(Backend::Ptrace);
+                args.cmd = std::mem::take(cmd);
+            }
+            Some(Command::Diff { .. }) => {
+                if args.input.is_some() || args.pid.is_some() || !args.cmd.is_empty() {
+                    conflict("diff can't be used with --input, --pid or a command");
+                }
+                if args.format != Format::Text || args.group_by.is_some() || args.tree {
+                    conflict("diff can't be used with --format, --group-by or --tree");
+                }
+            }
+            None => {}
+        }
 
         args
     }
 
+    /// Returns the filter of paths given by --include, --exclude, --regex and --exclude-set.
+    pub fn path_filter(&self) -> PathFilter {
+        let mut exclude = self.exclude.clone();
+        for set in &self.exclude_sets {
+            // NOTE: the globs of each set are known to be valid
+            exclude.extend(set.globs().iter().map(|glob| Glob::new(glob).unwrap()));
+        }
+
+        PathFilter {
+            include: self.include.iter().map(Glob::compile_matcher).collect(),
+            exclude: exclude.iter().map(Glob::compile_matcher).collect(),
+            regexes: self.regexes.clone(),
+        }
+    }
+
     pub fn file_types(&self) -> Option<&FileTypes> {
         self._file_types.as_ref()
     }
 
+    pub fn access_modes(&self) -> Option<&AccessModes> {
+        self._access_modes.as_ref()
+    }
+
     fn parse_file_types(input: &Vec<String>) -> Option<FileTypes> {
         if input.is_empty() {
             None
@@ -128,4 +506,23 @@ impl Args {
             Some(file_types)
         }
     }
+
+    fn parse_access_modes(input: &[String]) -> Option<AccessModes> {
+        if input.is_empty() {
+            None
+        } else {
+            let mut access_modes = AccessModes::default();
+            for m in input {
+                match m.as_str() {
+                    "r" | "read" => access_modes.read = true,
+                    "w" | "write" => access_modes.write = true,
+                    "rw" | "read-write" => access_modes.read_write = true,
+                    "?" | "unknown" => access_modes.unknown = true,
+                    _ => unreachable!(),
+                }
+            }
+
+            Some(access_modes)
+        }
+    }
 }
@@ -0,0 +1,162 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::{crate_name, IntoApp};
+use toml::value::{Table, Value};
+
+use crate::cli::Args;
+
+/// The name of the project's configuration file.
+const PROJECT_FILE_NAME: &str = ".ftrace.toml";
+
+/// Returns the paths of the configuration files that exist, in order of increasing precedence.
+fn paths() -> Vec<PathBuf> {
+    let user_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+    let user = user_dir.map(|dir| dir.join(crate_name!()).join("config.toml"));
+    let project = env::current_dir().ok().and_then(|dir| {
+        dir.ancestors()
+            .map(|dir| dir.join(PROJECT_FILE_NAME))
+            .find(|path| path.is_file())
+    });
+
+    user.into_iter()
+        .chain(project)
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// The options and profiles from every configuration file, where later files replace the options of earlier ones.
+#[derive(Debug, Default)]
+struct Config {
+    options: Table,
+    profiles: Table,
+}
+
+impl Config {
+    fn load(paths: &[PathBuf]) -> Result<Config> {
+        let mut config = Config::default();
+        for path in paths {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let mut options = contents
+                .parse::<Value>()
+                .with_context(|| format!("Failed to parse {}", path.display()))?
+                .try_into::<Table>()
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+            match options.remove("profile") {
+                Some(Value::Table(profiles)) => {
+                    for (name, profile) in profiles {
+                        match (config.profiles.get_mut(&name), profile) {
+                            (Some(Value::Table(existing)), Value::Table(profile)) => {
+                                existing.extend(profile)
+                            }
+                            (_, profile) => {
+                                config.profiles.insert(name, profile);
+                            }
+                        }
+                    }
+                }
+                Some(_) => return Err(anyhow!("Invalid profiles in {}", path.display())),
+                None => {}
+            }
+
+            config.options.extend(options);
+        }
+
+        Ok(config)
+    }
+
+    /// Returns the options, along with those of the given profile.
+    fn options(mut self, profile: Option<&str>) -> Result<Table> {
+        if let Some(name) = profile {
+            match self.profiles.remove(name) {
+                Some(Value::Table(profile)) => self.options.extend(profile),
+                Some(_) => return Err(anyhow!("Invalid profile: {}", name)),
+                None => return Err(anyhow!("Unknown profile: {}", name)),
+            }
+        }
+
+        Ok(self.options)
+    }
+}
+
+/// Converts the value of an option into command line arguments, e.g.: `type = ["f", "x"]` is `--type f --type x`.
+fn to_args(name: &str, value: &Value) -> Result<Vec<String>> {
+    let flag = format!("--{}", name);
+    Ok(match value {
+        Value::Boolean(true) => vec![flag],
+        Value::Boolean(false) => vec![],
+        Value::String(s) => vec![flag, s.clone()],
+        Value::Integer(n) => vec![flag, n.to_string()],
+        Value::Float(n) => vec![flag, n.to_string()],
+        Value::Array(values) => {
+            let mut args = vec![];
+            for value in values {
+                match value {
+                    Value::Array(_) | Value::Boolean(_) => {
+                        return Err(anyhow!("Invalid value for `{}`: {}", name, value))
+                    }
+                    value => args.extend(to_args(name, value)?),
+                }
+            }
+
+            args
+        }
+        Value::Datetime(_) | Value::Table(_) => {
+            return Err(anyhow!("Invalid value for `{}`: {}", name, value))
+        }
+    })
+}
+
+/// Inserts the options from the configuration files into the command line arguments, skipping any options that were
+/// already given on the command line.
+///
+/// Each key of a file is the long name of an option, and profiles are tables under `profile`. The options of a
+/// profile replace those of the files, which replace those of earlier files.
+pub fn apply(args: Vec<OsString>) -> Result<Vec<OsString>> {
+    // errors (and --help) are reported when the arguments are parsed again
+    let app = Args::into_app();
+    let matches = match app.clone().try_get_matches_from(&args) {
+        Ok(matches) => matches,
+        Err(_) => return Ok(args),
+    };
+
+    let profile = matches.value_of("profile");
+    let paths = paths();
+    if paths.is_empty() {
+        return match profile {
+            Some(name) => Err(anyhow!(
+                "Unknown profile: {} (no config file was found)",
+                name
+            )),
+            None => Ok(args),
+        };
+    }
+
+    let mut config_args = vec![];
+    for (name, value) in Config::load(&paths)?.options(profile)? {
+        let arg = app
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(&name) && name != "profile")
+            .ok_or_else(|| anyhow!("Unknown option in config: {}", name))?;
+        if matches.occurrences_of(arg.get_name()) == 0 {
+            config_args.extend(to_args(&name, &value)?.into_iter().map(OsString::from));
+        }
+    }
+
+    log::trace!("options from {:?}: {:?}", paths, config_args);
+    let mut args = args.into_iter();
+    Ok(args
+        .next()
+        .into_iter()
+        .chain(config_args)
+        .chain(args)
+        .collect())
+}
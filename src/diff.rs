@@ -0,0 +1,357 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::ArgEnum;
+use lazy_static::lazy_static;
+use regex::bytes::Regex;
+use serde::Deserialize;
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+use ftrace::filter::PathFilter;
+use ftrace::{Event, Tracer};
+
+use crate::output::Quote;
+
+lazy_static! {
+    /// Dates and times such as `2021-01-29`, `2021-01-29T10:31:13` or `20210129-103113`, and Unix timestamps.
+    static ref TIMESTAMP: Regex = Regex::new(
+        r"(?-u)\d{4}-\d{2}-\d{2}(?:[T_ ]\d{2}[:.-]?\d{2}(?:[:.-]?\d{2})?)?|\d{8}[T_-]\d{6}|\d{10,}"
+    )
+    .unwrap();
+}
+
+/// The minimum length of a part of a name that may have been randomly generated, such as by `mktemp`.
+const RANDOM_LEN: usize = 6;
+
+/// Which parts of paths are normalised before they're compared, since they usually differ between runs.
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+pub enum Normalize {
+    /// Pids in `/proc` become `<pid>`, e.g.: `/proc/<pid>/maps`
+    Pids,
+    /// Dates, times and Unix timestamps become `<timestamp>`
+    Timestamps,
+    /// Random names in temporary directories become `XXXXXX`, e.g.: `/tmp/tmp.XXXXXX`
+    TempDirs,
+    /// All of the above
+    All,
+}
+
+impl Normalize {
+    fn enabled(normalize: &[Normalize], what: Normalize) -> bool {
+        normalize.iter().any(|n| *n == what || *n == Normalize::All)
+    }
+}
+
+/// How a path was accessed throughout a trace.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Usage {
+    read: bool,
+    write: bool,
+    succeeded: bool,
+    failed: bool,
+}
+
+impl Usage {
+    fn mode(&self) -> &'static str {
+        match (self.read, self.write) {
+            (true, true) => "rw",
+            (true, false) => "r",
+            (false, true) => "w",
+            (false, false) => "?",
+        }
+    }
+
+    /// Describes how a path that's only in one of the traces was accessed, e.g.: `r` or `r, missing`.
+    fn describe(&self) -> Vec<String> {
+        let mut details = vec![self.mode().to_string()];
+        if self.found() == Some(false) {
+            details.push("missing".to_string());
+        }
+        details
+    }
+
+    /// Whether the path was found, which is only known if every access to it failed or at least one succeeded.
+    fn found(&self) -> Option<bool> {
+        match (self.succeeded, self.failed) {
+            (true, _) => Some(true),
+            (false, true) => Some(false),
+            (false, false) => None,
+        }
+    }
+}
+
+/// An access as it's printed by `--format jsonl`, with only the fields that are compared.
+#[derive(Debug, Deserialize)]
+struct Record {
+    path_bytes: String,
+    success: Option<bool>,
+    mode: String,
+}
+
+/// The paths accessed in a saved trace, and how each one was accessed.
+#[derive(Debug, Default)]
+pub struct Summary {
+    paths: BTreeMap<PathBuf, Usage>,
+}
+
+impl Summary {
+    /// Reads a trace saved with `--format jsonl`, or the raw output of `strace`.
+    pub fn load(path: &Path, filter: &PathFilter, normalize: &[Normalize]) -> Result<Summary> {
+        let contents =
+            fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut summary = Summary::default();
+        let mut add = |path: &Path, mode: (bool, bool), success: Option<bool>| {
+            if !filter.matches(path) {
+                return;
+            }
+
+            let usage = summary
+                .paths
+                .entry(normalize_path(path, normalize))
+                .or_default();
+            usage.read |= mode.0;
+            usage.write |= mode.1;
+            usage.succeeded |= success == Some(true);
+            usage.failed |= success == Some(false);
+        };
+
+        let is_json = contents.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{');
+        if is_json {
+            // NOTE: like `strace` output, any line that isn't recognised is ignored (e.g.: the traced program's output)
+            for (i, line) in contents.split(|b| *b == b'\n').enumerate() {
+                let record = match serde_json::from_slice::<Record>(line) {
+                    Ok(record) => record,
+                    Err(e) => {
+                        log::debug!("skipping line {} of {}: {}", i + 1, path.display(), e);
+                        continue;
+                    }
+                };
+                let bytes = hex::decode(&record.path_bytes).with_context(|| {
+                    format!("Failed to parse line {} of {}", i + 1, path.display())
+                })?;
+                let mode = (record.mode.contains('r'), record.mode.contains('w'));
+                add(Path::new(OsStr::from_bytes(&bytes)), mode, record.success);
+            }
+        } else {
+            let mut trace = Tracer::input(Cursor::new(contents))
+                .non_existent(true)
+                .spawn()?;
+            for event in &mut trace {
+                if let Event::Access(access) = event {
+                    let mode = (access.mode.is_read(), access.mode.is_write());
+                    add(&access.path, mode, access.success);
+                }
+            }
+            trace.wait()?;
+        }
+
+        if summary.paths.is_empty() {
+            return Err(anyhow!("No paths were found in {}", path.display()));
+        }
+
+        Ok(summary)
+    }
+}
+
+/// Prints the paths that were added (`+`) and removed (`-`) between two traces along with how they were accessed, and
+/// those whose access mode changed or that switched between being found and missing (`~`).
+///
+/// Returns whether there were any differences.
+pub fn write(a: &Summary, b: &Summary, quote: Quote, color: bool) -> io::Result<bool> {
+    let mut stdout = StandardStream::stdout(crate::choice!(color));
+    let mut paths = a.paths.keys().chain(b.paths.keys()).collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+
+    let mut differs = false;
+    for path in paths {
+        let (sign, color, details) = match (a.paths.get(path), b.paths.get(path)) {
+            (Some(a), None) => ("-", Color::Red, a.describe()),
+            (None, Some(b)) => ("+", Color::Green, b.describe()),
+            (Some(a), Some(b)) => {
+                let mut details = vec![];
+                if a.mode() != b.mode() {
+                    details.push(format!("{} -> {}", a.mode(), b.mode()));
+                }
+                if let (Some(a), Some(b)) = (a.found(), b.found()) {
+                    if a != b {
+                        let describe = |found| if found { "found" } else { "missing" };
+                        details.push(format!("{} -> {}", describe(a), describe(b)));
+                    }
+                }
+                if details.is_empty() {
+                    continue;
+                }
+                ("~", Color::Yellow, details)
+            }
+            (None, None) => unreachable!(),
+        };
+
+        differs = true;
+        let mut line = format!("{} ", sign).into_bytes();
+        line.extend(quote.quote(path));
+        line.extend(format!(" ({})", details.join(", ")).as_bytes());
+        line.push(b'\n');
+        stdout.set_color(ColorSpec::new().set_fg(Some(color)))?;
+        stdout.write_all(&line)?;
+    }
+    stdout.reset()?;
+
+    Ok(differs)
+}
+
+/// Replaces the parts of a path that usually differ between runs, such as pids and the names of temporary files.
+fn normalize_path(path: &Path, normalize: &[Normalize]) -> PathBuf {
+    let mut path = path.as_os_str().as_bytes().to_vec();
+    if Normalize::enabled(normalize, Normalize::Pids) {
+        path = normalize_pids(&path);
+    }
+    if Normalize::enabled(normalize, Normalize::Timestamps) {
+        path = TIMESTAMP
+            .replace_all(&path, &b"<timestamp>"[..])
+            .into_owned();
+    }
+    if Normalize::enabled(normalize, Normalize::TempDirs) {
+        path = normalize_temp_dirs(&path);
+    }
+
+    PathBuf::from(OsStr::from_bytes(&path))
+}
+
+/// Replaces the pids in paths such as `/proc/1234/maps` and `/proc/1234/task/1235/stat` with `<pid>`.
+fn normalize_pids(path: &[u8]) -> Vec<u8> {
+    let mut components = path.split(|b| *b == b'/').collect::<Vec<_>>();
+    let is_pid = |c: &[u8]| !c.is_empty() && c.iter().all(u8::is_ascii_digit);
+    if components.len() > 2 && components[0].is_empty() && components[1] == b"proc" {
+        if is_pid(components[2]) {
+            components[2] = b"<pid>";
+        }
+        if components.len() > 4 && components[3] == b"task" && is_pid(components[4]) {
+            components[4] = b"<pid>";
+        }
+    }
+
+    components.join(&b'/')
+}
+
+/// Replaces the parts of names beneath a temporary directory that look randomly generated (i.e.: long runs of
+/// letters and digits that mix lowercase, uppercase or digits) with `XXXXXX`, e.g.: `/tmp/tmp.a8Zk3l/out`.
+fn normalize_temp_dirs(path: &[u8]) -> Vec<u8> {
+    let mut temp_dirs = vec![b"/tmp".to_vec(), b"/var/tmp".to_vec(), b"/dev/shm".to_vec()];
+    if let Some(dir) = env::var_os("TMPDIR") {
+        temp_dirs.push(dir.as_bytes().to_vec());
+    }
+    let rest = temp_dirs.iter().find_map(|dir| {
+        let dir = dir.strip_suffix(b"/").unwrap_or(dir);
+        path.strip_prefix(dir)
+            .filter(|rest| rest.starts_with(b"/"))
+            .map(|rest| (dir.len(), rest))
+    });
+    let (len, rest) = match rest {
+        Some(rest) => rest,
+        None => return path.to_vec(),
+    };
+
+    let mut normalized = path[..len].to_vec();
+    for run in rest.split_inclusive(|b| !b.is_ascii_alphanumeric()) {
+        let (word, separator) = match run.last() {
+            Some(b) if !b.is_ascii_alphanumeric() => run.split_at(run.len() - 1),
+            _ => (run, &b""[..]),
+        };
+        let classes = [
+            word.iter().any(u8::is_ascii_lowercase),
+            word.iter().any(u8::is_ascii_uppercase),
+            word.iter().any(u8::is_ascii_digit),
+        ];
+        if word.len() >= RANDOM_LEN && classes.iter().filter(|c| **c).count() >= 2 {
+            normalized.extend(b"XXXXXX");
+        } else {
+            normalized.extend(word);
+        }
+        normalized.extend(separator);
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{normalize_path, normalize_pids, normalize_temp_dirs, Normalize};
+
+    #[test]
+    fn pids() {
+        assert_eq!(normalize_pids(b"/proc/1234/maps"), b"/proc/<pid>/maps");
+        assert_eq!(
+            normalize_pids(b"/proc/1234/task/1235/stat"),
+            b"/proc/<pid>/task/<pid>/stat"
+        );
+        assert_eq!(normalize_pids(b"/proc/1234"), b"/proc/<pid>");
+        assert_eq!(normalize_pids(b"/proc/self/maps"), b"/proc/self/maps");
+        assert_eq!(normalize_pids(b"/proc/12a/maps"), b"/proc/12a/maps");
+        assert_eq!(normalize_pids(b"/home/proc/1234"), b"/home/proc/1234");
+        assert_eq!(normalize_pids(b"proc/1234"), b"proc/1234");
+    }
+
+    #[test]
+    fn temp_dirs() {
+        assert_eq!(
+            normalize_temp_dirs(b"/tmp/tmp.a8Zk3l/out.txt"),
+            b"/tmp/tmp.XXXXXX/out.txt"
+        );
+        assert_eq!(
+            normalize_temp_dirs(b"/var/tmp/build-Q7x2Lm9a/cache"),
+            b"/var/tmp/build-XXXXXX/cache"
+        );
+        // names without a mix of lowercase, uppercase and digits, or that are too short, are kept
+        assert_eq!(
+            normalize_temp_dirs(b"/tmp/output/a1b2/report"),
+            b"/tmp/output/a1b2/report"
+        );
+        assert_eq!(normalize_temp_dirs(b"/tmpfoo/a8Zk3l"), b"/tmpfoo/a8Zk3l");
+        assert_eq!(normalize_temp_dirs(b"/home/a8Zk3l"), b"/home/a8Zk3l");
+    }
+
+    #[test]
+    fn timestamps() {
+        let normalize = |path: &str| normalize_path(Path::new(path), &[Normalize::Timestamps]);
+        assert_eq!(
+            normalize("/logs/2021-01-29.log"),
+            Path::new("/logs/<timestamp>.log")
+        );
+        assert_eq!(
+            normalize("/logs/build-2021-01-29T10:31:13.log"),
+            Path::new("/logs/build-<timestamp>.log")
+        );
+        assert_eq!(
+            normalize("/logs/2021-01-29_10-31.log"),
+            Path::new("/logs/<timestamp>.log")
+        );
+        assert_eq!(
+            normalize("/logs/20210129-103113/out"),
+            Path::new("/logs/<timestamp>/out")
+        );
+        assert_eq!(
+            normalize("/cache/1611916400.json"),
+            Path::new("/cache/<timestamp>.json")
+        );
+        // too short to be a Unix timestamp
+        assert_eq!(normalize("/cache/161191640"), Path::new("/cache/161191640"));
+        // other normalisations only apply when they're enabled
+        assert_eq!(
+            normalize("/proc/1234/tmp.a8Zk3l"),
+            Path::new("/proc/1234/tmp.a8Zk3l")
+        );
+        assert_eq!(
+            normalize_path(Path::new("/proc/1234/2021-01-29"), &[Normalize::All]),
+            Path::new("/proc/<pid>/<timestamp>")
+        );
+    }
+}
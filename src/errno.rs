@@ -0,0 +1,193 @@
+use std::ffi::CStr;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+const ERRNO_NAMES: &[(i32, &str)] = &[
+    (libc::EPERM, "EPERM"),
+    (libc::ENOENT, "ENOENT"),
+    (libc::ESRCH, "ESRCH"),
+    (libc::EINTR, "EINTR"),
+    (libc::EIO, "EIO"),
+    (libc::ENXIO, "ENXIO"),
+    (libc::E2BIG, "E2BIG"),
+    (libc::ENOEXEC, "ENOEXEC"),
+    (libc::EBADF, "EBADF"),
+    (libc::ECHILD, "ECHILD"),
+    (libc::EAGAIN, "EAGAIN"),
+    (libc::ENOMEM, "ENOMEM"),
+    (libc::EACCES, "EACCES"),
+    (libc::EFAULT, "EFAULT"),
+    (libc::ENOTBLK, "ENOTBLK"),
+    (libc::EBUSY, "EBUSY"),
+    (libc::EEXIST, "EEXIST"),
+    (libc::EXDEV, "EXDEV"),
+    (libc::ENODEV, "ENODEV"),
+    (libc::ENOTDIR, "ENOTDIR"),
+    (libc::EISDIR, "EISDIR"),
+    (libc::EINVAL, "EINVAL"),
+    (libc::ENFILE, "ENFILE"),
+    (libc::EMFILE, "EMFILE"),
+    (libc::ENOTTY, "ENOTTY"),
+    (libc::ETXTBSY, "ETXTBSY"),
+    (libc::EFBIG, "EFBIG"),
+    (libc::ENOSPC, "ENOSPC"),
+    (libc::ESPIPE, "ESPIPE"),
+    (libc::EROFS, "EROFS"),
+    (libc::EMLINK, "EMLINK"),
+    (libc::EPIPE, "EPIPE"),
+    (libc::EDOM, "EDOM"),
+    (libc::ERANGE, "ERANGE"),
+    (libc::EDEADLK, "EDEADLK"),
+    (libc::ENAMETOOLONG, "ENAMETOOLONG"),
+    (libc::ENOLCK, "ENOLCK"),
+    (libc::ENOSYS, "ENOSYS"),
+    (libc::ENOTEMPTY, "ENOTEMPTY"),
+    (libc::ELOOP, "ELOOP"),
+    (libc::ENOMSG, "ENOMSG"),
+    (libc::EIDRM, "EIDRM"),
+    (libc::ECHRNG, "ECHRNG"),
+    (libc::EL2NSYNC, "EL2NSYNC"),
+    (libc::EL3HLT, "EL3HLT"),
+    (libc::EL3RST, "EL3RST"),
+    (libc::ELNRNG, "ELNRNG"),
+    (libc::EUNATCH, "EUNATCH"),
+    (libc::ENOCSI, "ENOCSI"),
+    (libc::EL2HLT, "EL2HLT"),
+    (libc::EBADE, "EBADE"),
+    (libc::EBADR, "EBADR"),
+    (libc::EXFULL, "EXFULL"),
+    (libc::ENOANO, "ENOANO"),
+    (libc::EBADRQC, "EBADRQC"),
+    (libc::EBADSLT, "EBADSLT"),
+    (libc::EBFONT, "EBFONT"),
+    (libc::ENOSTR, "ENOSTR"),
+    (libc::ENODATA, "ENODATA"),
+    (libc::ETIME, "ETIME"),
+    (libc::ENOSR, "ENOSR"),
+    (libc::ENONET, "ENONET"),
+    (libc::ENOPKG, "ENOPKG"),
+    (libc::EREMOTE, "EREMOTE"),
+    (libc::ENOLINK, "ENOLINK"),
+    (libc::EADV, "EADV"),
+    (libc::ESRMNT, "ESRMNT"),
+    (libc::ECOMM, "ECOMM"),
+    (libc::EPROTO, "EPROTO"),
+    (libc::EMULTIHOP, "EMULTIHOP"),
+    (libc::EDOTDOT, "EDOTDOT"),
+    (libc::EBADMSG, "EBADMSG"),
+    (libc::EOVERFLOW, "EOVERFLOW"),
+    (libc::ENOTUNIQ, "ENOTUNIQ"),
+    (libc::EBADFD, "EBADFD"),
+    (libc::EREMCHG, "EREMCHG"),
+    (libc::ELIBACC, "ELIBACC"),
+    (libc::ELIBBAD, "ELIBBAD"),
+    (libc::ELIBSCN, "ELIBSCN"),
+    (libc::ELIBMAX, "ELIBMAX"),
+    (libc::ELIBEXEC, "ELIBEXEC"),
+    (libc::EILSEQ, "EILSEQ"),
+    (libc::ERESTART, "ERESTART"),
+    (libc::ESTRPIPE, "ESTRPIPE"),
+    (libc::EUSERS, "EUSERS"),
+    (libc::ENOTSOCK, "ENOTSOCK"),
+    (libc::EDESTADDRREQ, "EDESTADDRREQ"),
+    (libc::EMSGSIZE, "EMSGSIZE"),
+    (libc::EPROTOTYPE, "EPROTOTYPE"),
+    (libc::ENOPROTOOPT, "ENOPROTOOPT"),
+    (libc::EPROTONOSUPPORT, "EPROTONOSUPPORT"),
+    (libc::ESOCKTNOSUPPORT, "ESOCKTNOSUPPORT"),
+    (libc::ENOTSUP, "EOPNOTSUPP"),
+    (libc::EPFNOSUPPORT, "EPFNOSUPPORT"),
+    (libc::EAFNOSUPPORT, "EAFNOSUPPORT"),
+    (libc::EADDRINUSE, "EADDRINUSE"),
+    (libc::EADDRNOTAVAIL, "EADDRNOTAVAIL"),
+    (libc::ENETDOWN, "ENETDOWN"),
+    (libc::ENETUNREACH, "ENETUNREACH"),
+    (libc::ENETRESET, "ENETRESET"),
+    (libc::ECONNABORTED, "ECONNABORTED"),
+    (libc::ECONNRESET, "ECONNRESET"),
+    (libc::ENOBUFS, "ENOBUFS"),
+    (libc::EISCONN, "EISCONN"),
+    (libc::ENOTCONN, "ENOTCONN"),
+    (libc::ESHUTDOWN, "ESHUTDOWN"),
+    (libc::ETOOMANYREFS, "ETOOMANYREFS"),
+    (libc::ETIMEDOUT, "ETIMEDOUT"),
+    (libc::ECONNREFUSED, "ECONNREFUSED"),
+    (libc::EHOSTDOWN, "EHOSTDOWN"),
+    (libc::EHOSTUNREACH, "EHOSTUNREACH"),
+    (libc::EALREADY, "EALREADY"),
+    (libc::EINPROGRESS, "EINPROGRESS"),
+    (libc::ESTALE, "ESTALE"),
+    (libc::EUCLEAN, "EUCLEAN"),
+    (libc::ENOTNAM, "ENOTNAM"),
+    (libc::ENAVAIL, "ENAVAIL"),
+    (libc::EISNAM, "EISNAM"),
+    (libc::EREMOTEIO, "EREMOTEIO"),
+    (libc::EDQUOT, "EDQUOT"),
+    (libc::ENOMEDIUM, "ENOMEDIUM"),
+    (libc::EMEDIUMTYPE, "EMEDIUMTYPE"),
+    (libc::ECANCELED, "ECANCELED"),
+    (libc::ENOKEY, "ENOKEY"),
+    (libc::EKEYEXPIRED, "EKEYEXPIRED"),
+    (libc::EKEYREVOKED, "EKEYREVOKED"),
+    (libc::EKEYREJECTED, "EKEYREJECTED"),
+    (libc::EOWNERDEAD, "EOWNERDEAD"),
+    (libc::ENOTRECOVERABLE, "ENOTRECOVERABLE"),
+    (libc::ERFKILL, "ERFKILL"),
+    (libc::EHWPOISON, "EHWPOISON"),
+];
+
+/// An error number, such as one returned by a failed syscall.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Errno(pub i32);
+
+impl Errno {
+    /// Returns the error with the given symbolic name, e.g.: `ENOENT`.
+    pub fn from_name(name: &str) -> Option<Errno> {
+        ERRNO_NAMES
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(errno, _)| Errno(*errno))
+    }
+
+    /// Returns the symbolic name of the error, e.g.: `ENOENT`.
+    pub fn name(&self) -> Option<&'static str> {
+        ERRNO_NAMES
+            .iter()
+            .find(|(n, _)| *n == self.0)
+            .map(|(_, name)| *name)
+    }
+
+    /// Returns the human readable description of the error, e.g.: `No such file or directory`.
+    pub fn message(&self) -> String {
+        unsafe { CStr::from_ptr(libc::strerror(self.0)) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Formats the error in the same way as `strace`, e.g.: `ENOENT (No such file or directory)`.
+impl Display for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{} ({})", name, self.message()),
+            None => write!(f, "{} ({})", self.0, self.message()),
+        }
+    }
+}
+
+impl FromStr for Errno {
+    type Err = anyhow::Error;
+
+    /// Parses either a symbolic name (e.g.: `ENOENT`) or a number.
+    fn from_str(s: &str) -> Result<Errno> {
+        match Errno::from_name(s) {
+            Some(errno) => Ok(errno),
+            None => s
+                .parse()
+                .map(Errno)
+                .map_err(|_| anyhow!("Unknown error name: {}", s)),
+        }
+    }
+}
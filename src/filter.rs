@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::str::FromStr;
+
+use clap::ArgEnum;
+use globset::{Glob, GlobMatcher};
+use regex::bytes::Regex;
+
+use crate::analysis::AccessMode;
+use crate::fs;
+
+/// Which types of files to include: a path is included if it matches every type that's set.
+#[derive(Debug, Default, Clone)]
+pub struct FileTypes {
+    pub files: bool,
+    pub directories: bool,
+    pub symlinks: bool,
+    pub sockets: bool,
+    pub pipes: bool,
+    pub executables: bool,
+    pub empty: bool,
+}
+
+impl FileTypes {
+    pub fn matches(&self, path: &Path) -> bool {
+        match path.metadata() {
+            Ok(meta) => {
+                let ft = meta.file_type();
+                !((self.files && !fs::is_file(path))
+                    || (self.directories && !fs::is_dir(path))
+                    || (self.symlinks && !fs::is_symlink(path))
+                    || (self.sockets && !fs::is_socket(&ft))
+                    || (self.pipes && !fs::is_pipe(&ft))
+                    || (self.executables && !fs::is_executable(&meta))
+                    || (self.empty && !fs::is_empty(path)))
+            }
+            // NOTE: skip here because the string was probably not a valid path?
+            Err(_) => false,
+        }
+    }
+}
+
+/// Which access modes to include: a path is included if it matches any mode that's set.
+#[derive(Debug, Default, Clone)]
+pub struct AccessModes {
+    pub read: bool,
+    pub write: bool,
+    pub read_write: bool,
+    pub unknown: bool,
+}
+
+impl AccessModes {
+    pub fn matches(&self, mode: AccessMode) -> bool {
+        (self.read && mode.is_read())
+            || (self.write && mode.is_write())
+            || (self.read_write && mode == AccessMode::ReadWrite)
+            || (self.unknown && mode == AccessMode::Unknown)
+    }
+}
+
+/// A pattern matching the program that a process is running, which is either a name or a glob (e.g.: `cc1` or `ld*`).
+/// Patterns containing a `/` are matched against the program's absolute path, and otherwise against its file name.
+#[derive(Debug, Clone)]
+pub struct ExePattern {
+    matcher: GlobMatcher,
+    full_path: bool,
+}
+
+impl ExePattern {
+    pub fn matches(&self, exe: &Path) -> bool {
+        if self.full_path {
+            self.matcher.is_match(exe)
+        } else {
+            exe.file_name()
+                .map(|name| self.matcher.is_match(name))
+                .unwrap_or(false)
+        }
+    }
+}
+
+impl FromStr for ExePattern {
+    type Err = globset::Error;
+
+    fn from_str(s: &str) -> Result<ExePattern, globset::Error> {
+        Ok(ExePattern {
+            matcher: Glob::new(s)?.compile_matcher(),
+            full_path: s.contains('/'),
+        })
+    }
+}
+
+/// Which processes to include paths from, by their pid or by the program they're running.
+#[derive(Debug, Default, Clone)]
+pub struct ProcessFilter {
+    /// Only include processes with one of these pids (if any are set)
+    pub only_pids: HashSet<i32>,
+    /// Only include processes running a program that matches one of these patterns (if any are set)
+    pub only_exes: Vec<ExePattern>,
+    /// Exclude processes running a program that matches any of these patterns
+    pub exclude_exes: Vec<ExePattern>,
+    /// Whether the descendants of included (or excluded) processes are also included (or excluded)
+    pub descendants: bool,
+}
+
+impl ProcessFilter {
+    /// Whether only some processes are included, in which case every other process is excluded.
+    pub fn is_only(&self) -> bool {
+        !self.only_pids.is_empty() || !self.only_exes.is_empty()
+    }
+
+    /// Whether a process matches the `only_*` filters, ignoring its ancestors.
+    pub fn matches_only(&self, pid: Option<i32>, exe: Option<&Path>) -> bool {
+        pid.map(|pid| self.only_pids.contains(&pid))
+            .unwrap_or(false)
+            || exe
+                .map(|exe| self.only_exes.iter().any(|pattern| pattern.matches(exe)))
+                .unwrap_or(false)
+    }
+
+    /// Whether a process matches the `exclude_*` filters, ignoring its ancestors.
+    pub fn matches_exclude(&self, exe: Option<&Path>) -> bool {
+        exe.map(|exe| self.exclude_exes.iter().any(|pattern| pattern.matches(exe)))
+            .unwrap_or(false)
+    }
+}
+
+/// A built-in set of paths to exclude.
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+pub enum ExcludeSet {
+    /// Paths that are accessed by almost every program: `/proc`, `/sys`, `/dev`, locales and the dynamic loader's cache
+    System,
+}
+
+impl ExcludeSet {
+    /// The globs of the paths in the set.
+    pub fn globs(&self) -> &'static [&'static str] {
+        match self {
+            ExcludeSet::System => &[
+                "/proc",
+                "/proc/**",
+                "/sys",
+                "/sys/**",
+                "/dev",
+                "/dev/**",
+                "/usr/lib/locale",
+                "/usr/lib/locale/**",
+                "/etc/ld.so.cache",
+                "/etc/ld.so.preload",
+            ],
+        }
+    }
+}
+
+/// Which paths to include by matching them against globs and regular expressions.
+///
+/// A path is included if it matches any of the `include` globs or `regexes` (or if there aren't any), unless it
+/// matches any of the `exclude` globs.
+#[derive(Debug, Default, Clone)]
+pub struct PathFilter {
+    pub include: Vec<GlobMatcher>,
+    pub exclude: Vec<GlobMatcher>,
+    pub regexes: Vec<Regex>,
+}
+
+impl PathFilter {
+    pub fn matches(&self, path: &Path) -> bool {
+        let included = (self.include.is_empty() && self.regexes.is_empty())
+            || self.include.iter().any(|glob| glob.is_match(path))
+            || self
+                .regexes
+                .iter()
+                .any(|re| re.is_match(path.as_os_str().as_bytes()));
+
+        included && !self.exclude.iter().any(|glob| glob.is_match(path))
+    }
+}
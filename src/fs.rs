@@ -1,6 +1,6 @@
 use std::fs;
 use std::os::unix::fs::{FileTypeExt, PermissionsExt};
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 
 pub fn is_file(path: &Path) -> bool {
     path.is_file()
@@ -43,3 +43,77 @@ pub fn is_empty(path: &Path) -> bool {
         false
     }
 }
+
+/// Lexically normalises a path, removing `.` components and resolving `..` components without touching the filesystem.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Returns `path` relative to `base`, where both are absolute and normalised.
+pub fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let path = path.components().collect::<Vec<_>>();
+    let base = base.components().collect::<Vec<_>>();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &path[common..] {
+        relative.push(component);
+    }
+
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+
+    relative
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{normalize, relative_to};
+
+    #[test]
+    fn normalize_paths() {
+        assert_eq!(normalize(Path::new("/a/./b/../c/")), Path::new("/a/c"));
+        assert_eq!(normalize(Path::new("/../a")), Path::new("/a"));
+        assert_eq!(normalize(Path::new("a/../../b")), Path::new("../b"));
+        assert_eq!(normalize(Path::new("../../b")), Path::new("../../b"));
+    }
+
+    #[test]
+    fn relative_paths() {
+        let base = Path::new("/home/user/project");
+        assert_eq!(
+            relative_to(Path::new("/home/user/project/src/main.rs"), base),
+            Path::new("src/main.rs")
+        );
+        assert_eq!(
+            relative_to(Path::new("/home/user/other"), base),
+            Path::new("../other")
+        );
+        assert_eq!(
+            relative_to(Path::new("/etc/passwd"), base),
+            Path::new("../../../etc/passwd")
+        );
+        assert_eq!(relative_to(base, base), Path::new("."));
+    }
+}
@@ -0,0 +1,296 @@
+//! A minimal wrapper around the Landlock syscalls, which restrict the paths that a process (and its descendants) can
+//! access. See `landlock(7)` for how the access rights apply.
+
+use std::ffi::{CStr, CString};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::Path;
+use std::{io, mem};
+
+use libc::{c_int, c_long, c_void};
+
+pub const EXECUTE: u64 = 1 << 0;
+pub const WRITE_FILE: u64 = 1 << 1;
+pub const READ_FILE: u64 = 1 << 2;
+pub const READ_DIR: u64 = 1 << 3;
+pub const REMOVE_DIR: u64 = 1 << 4;
+pub const REMOVE_FILE: u64 = 1 << 5;
+pub const MAKE_CHAR: u64 = 1 << 6;
+pub const MAKE_DIR: u64 = 1 << 7;
+pub const MAKE_REG: u64 = 1 << 8;
+pub const MAKE_SOCK: u64 = 1 << 9;
+pub const MAKE_FIFO: u64 = 1 << 10;
+pub const MAKE_BLOCK: u64 = 1 << 11;
+pub const MAKE_SYM: u64 = 1 << 12;
+/// Linking or renaming a file into another directory (added in ABI version 2)
+pub const REFER: u64 = 1 << 13;
+/// Truncating a file (added in ABI version 3)
+pub const TRUNCATE: u64 = 1 << 14;
+/// Calling `ioctl(2)` on a device (added in ABI version 5)
+pub const IOCTL_DEV: u64 = 1 << 15;
+
+/// The access rights that apply to files, as opposed to the contents of directories.
+pub const FILE_ACCESS: u64 = EXECUTE | WRITE_FILE | READ_FILE | TRUNCATE | IOCTL_DEV;
+
+const CREATE_RULESET_VERSION: u32 = 1 << 0;
+const RULE_PATH_BENEATH: c_int = 1;
+
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+}
+
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+/// Returns the latest version of the Landlock ABI that the kernel supports.
+pub fn abi() -> io::Result<i32> {
+    let version = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            std::ptr::null::<RulesetAttr>(),
+            0,
+            CREATE_RULESET_VERSION,
+        )
+    };
+    match version {
+        -1 => Err(io::Error::last_os_error()),
+        version => Ok(version as i32),
+    }
+}
+
+/// Returns the access rights that can be restricted by the given version of the ABI.
+fn supported(abi: i32) -> u64 {
+    let mut access = REFER - 1;
+    for (version, rights) in [(2, REFER), (3, TRUNCATE), (5, IOCTL_DEV)] {
+        if abi >= version {
+            access |= rights;
+        }
+    }
+
+    access
+}
+
+/// Returns the access rights of a rule that can be restricted, since rights that only apply to the contents of
+/// directories can't be allowed on a file.
+fn allowed(access: u64, handled: u64, is_dir: bool) -> u64 {
+    if is_dir {
+        access & handled
+    } else {
+        access & handled & FILE_ACCESS
+    }
+}
+
+/// Whether an open file descriptor refers to a directory.
+///
+/// NOTE: this is async-signal-safe, since it's called by `restrict_self`.
+fn is_dir(fd: &OwnedFd) -> bool {
+    let mut stat: libc::stat = unsafe { mem::zeroed() };
+    let result = unsafe { libc::fstat(fd.as_raw_fd(), &mut stat) };
+    result == 0 && stat.st_mode & libc::S_IFMT == libc::S_IFDIR
+}
+
+/// Opens a path so that it can be used in a rule.
+fn open(path: &CStr) -> io::Result<OwnedFd> {
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+    match fd {
+        -1 => Err(io::Error::last_os_error()),
+        fd => Ok(unsafe { OwnedFd::from_raw_fd(fd) }),
+    }
+}
+
+/// A set of paths and the access rights that are allowed beneath them, where every other access is denied.
+#[derive(Debug)]
+pub struct Ruleset {
+    fd: OwnedFd,
+    /// The access rights that are denied unless a rule allows them
+    handled: u64,
+    /// The rules added by `allow_own`, which are only opened (and have their rights filtered) by the process that
+    /// calls `restrict_self`
+    own: Vec<(CString, u64)>,
+}
+
+impl Ruleset {
+    /// Creates an empty ruleset that restricts every access right that the kernel supports.
+    pub fn new() -> io::Result<Ruleset> {
+        let handled = supported(abi()?);
+        let attr = RulesetAttr {
+            handled_access_fs: handled,
+        };
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0,
+            )
+        };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Ruleset {
+            fd: unsafe { OwnedFd::from_raw_fd(fd as c_int) },
+            handled,
+            own: vec![],
+        })
+    }
+
+    /// Allows the given access rights to a file, or to everything beneath a directory.
+    /// Rights that the kernel doesn't support are ignored, as are those that only apply to directories if the path is
+    /// a file.
+    pub fn allow(&mut self, path: &Path, access: u64) -> io::Result<()> {
+        let access = allowed(access, self.handled, path.is_dir());
+        if access == 0 {
+            return Ok(());
+        }
+
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        self.add_rule(&open(&path)?, access)
+    }
+
+    /// Allows the given access rights in the same way as `allow`, except that the path is opened by the process that
+    /// calls `restrict_self`. This is needed for paths that refer to the process that opens them, such as
+    /// `/proc/self/status`, which would otherwise refer to this process. The rule is skipped if the path doesn't
+    /// exist in that process.
+    pub fn allow_own(&mut self, path: &Path, access: u64) -> io::Result<()> {
+        // whether the path is a directory is only known once it's opened by the sandboxed process
+        let access = access & self.handled;
+        if access != 0 {
+            self.own
+                .push((CString::new(path.as_os_str().as_bytes())?, access));
+        }
+
+        Ok(())
+    }
+
+    fn add_rule(&self, fd: &OwnedFd, access: u64) -> io::Result<()> {
+        let attr = PathBeneathAttr {
+            allowed_access: access,
+            parent_fd: fd.as_raw_fd(),
+        };
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                self.fd.as_raw_fd(),
+                RULE_PATH_BENEATH,
+                &attr as *const PathBeneathAttr as *const c_void,
+                0,
+            )
+        };
+        match result {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Restricts the current thread (and any processes it creates) to the ruleset.
+    ///
+    /// NOTE: this only makes async-signal-safe calls, so it can be called between `fork(2)` and `execve(2)`.
+    pub fn restrict_self(&self) -> io::Result<()> {
+        for (path, access) in &self.own {
+            if let Ok(fd) = open(path) {
+                let access = allowed(*access, self.handled, is_dir(&fd));
+                if access != 0 {
+                    self.add_rule(&fd, access)?;
+                }
+            }
+        }
+
+        // required to restrict ourselves without `CAP_SYS_ADMIN`
+        let result = unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1 as c_long, 0, 0, 0) };
+        if result == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let result =
+            unsafe { libc::syscall(libc::SYS_landlock_restrict_self, self.fd.as_raw_fd(), 0) };
+        match result {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::path::Path;
+
+    use super::{
+        abi, allowed, is_dir, open, supported, Ruleset, EXECUTE, IOCTL_DEV, MAKE_REG, READ_DIR,
+        READ_FILE, REFER, REMOVE_FILE, TRUNCATE, WRITE_FILE,
+    };
+
+    #[test]
+    fn supported_rights() {
+        assert_eq!(supported(1), (1 << 13) - 1);
+        assert_eq!(supported(2), supported(1) | REFER);
+        assert_eq!(supported(3), supported(2) | TRUNCATE);
+        assert_eq!(supported(4), supported(3));
+        assert_eq!(supported(5), supported(3) | IOCTL_DEV);
+        assert_eq!(supported(7), supported(5));
+    }
+
+    #[test]
+    fn allowed_rights() {
+        let handled = supported(5);
+        assert_eq!(
+            allowed(READ_FILE | READ_DIR, handled, true),
+            READ_FILE | READ_DIR
+        );
+        assert_eq!(allowed(READ_FILE | READ_DIR, handled, false), READ_FILE);
+        assert_eq!(allowed(MAKE_REG | REMOVE_FILE | REFER, handled, false), 0);
+        assert_eq!(
+            allowed(EXECUTE | IOCTL_DEV, handled, false),
+            EXECUTE | IOCTL_DEV
+        );
+
+        // rights that the kernel doesn't support are ignored
+        assert_eq!(
+            allowed(WRITE_FILE | TRUNCATE | IOCTL_DEV, supported(2), false),
+            WRITE_FILE
+        );
+        assert_eq!(allowed(MAKE_REG | REFER, supported(1), true), MAKE_REG);
+    }
+
+    #[test]
+    fn directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        let open = |path: &Path| open(&CString::new(path.to_str().unwrap()).unwrap()).unwrap();
+
+        assert!(is_dir(&open(dir.path())));
+        assert!(!is_dir(&open(&file)));
+    }
+
+    #[test]
+    fn own_rules() {
+        if abi().is_err() {
+            return;
+        }
+
+        // rights that only apply to directories are kept until the path is opened by the sandboxed process
+        let mut ruleset = Ruleset::new().unwrap();
+        ruleset
+            .allow_own(Path::new("/proc/self/status"), READ_FILE | READ_DIR)
+            .unwrap();
+        ruleset
+            .allow_own(Path::new("/proc/self/missing"), MAKE_REG)
+            .unwrap();
+        assert_eq!(
+            ruleset.own,
+            vec![
+                (
+                    CString::new("/proc/self/status").unwrap(),
+                    READ_FILE | READ_DIR
+                ),
+                (CString::new("/proc/self/missing").unwrap(), MAKE_REG),
+            ]
+        );
+    }
+}
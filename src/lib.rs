@@ -0,0 +1,20 @@
+//! A library for discovering which files and paths a program accesses, which powers the `ftrace`
+//! binary.
+//!
+//! Programs are traced with either `strace` or natively via `ptrace(2)`, and each access of a path
+//! is reported as a [`FileAccess`]. See [`Tracer`] for an example.
+
+pub mod analysis;
+pub mod backend;
+pub mod errno;
+pub mod filter;
+mod fs;
+pub mod landlock;
+pub mod parse;
+pub mod policy;
+mod processes;
+mod signal;
+mod tracer;
+pub mod tree;
+
+pub use tracer::{Event, ExitStatus, FileAccess, Trace, Tracer};
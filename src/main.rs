@@ -2,10 +2,11 @@
 //!
 //! This tool's primary purpose is to assist in discovering which files/directories a program
 //! accesses during its lifetime. It works by making use of [`strace`] and parsing its output to
-//! find out which files and folders were accessed.
+//! find out which files and folders were accessed. If [`strace`] isn't installed, then it traces
+//! the program natively via `ptrace(2)` instead.
 //!
 //! It supports various options, such as filtering based on file type (file, directory, symlink,
-//! pipe, socket, executable, etc).
+//! pipe, socket, executable, etc) and access mode (read, write, etc).
 //!
 //! ## Usage
 //!
@@ -19,6 +20,11 @@
 //! ftrace --type f --type x -- ls
 //! ```
 //!
+//! See which files a program wrote to, along with the access mode of each:
+//! ```bash
+//! ftrace --access w --show-mode -- make
+//! ```
+//!
 //! See _all paths that the program **tried to access**_ (even ones that didn't exist). This is
 //! sometimes useful to understand a search algorithm that a program uses to find linked libraries,
 //! etc.
@@ -26,20 +32,261 @@
 //! ftrace --non-existent -- ls
 //! ```
 //!
+//! Paths are printed as absolute paths, resolved against each process's working directory. To
+//! print them relative to a directory instead:
+//! ```bash
+//! ftrace --relative-to . -- make
+//! ```
+//!
+//! See which paths a program was denied access to:
+//! ```bash
+//! ftrace --errno EACCES,EPERM -- make
+//! ```
+//!
+//! See which program in a build accessed each path, or the tree of processes that it created:
+//! ```bash
+//! ftrace --group-by process -- make
+//! ftrace --tree -- make
+//! ```
+//!
+//! Only see some paths, by matching them against globs or regular expressions, and hide the paths
+//! that almost every program accesses (such as `/proc`, `/dev` and locales):
+//! ```bash
+//! ftrace --include '/home/**' --exclude '*.so*' -- make
+//! ftrace --regex '\.(c|h)$' --exclude-set system -- make
+//! ```
+//!
+//! Only see the paths accessed by some of the programs that a build runs, optionally including
+//! any programs that they run in turn:
+//! ```bash
+//! ftrace --only-exe cc1 --only-exe 'ld*' -- make
+//! ftrace --only-exe make --exclude-exe sh --descendants -- make
+//! ```
+//!
+//! `ftrace` exits with the same exit code as the traced program (or 128 plus the signal number if
+//! it was killed by a signal), so it can be dropped into scripts and Makefiles as a wrapper:
+//! ```bash
+//! ftrace --access w -- make test || echo "make failed"
+//! ```
+//!
 //! Attach to an already running process (note that this requires elevated privileges):
 //! ```bash
 //! ftrace --pid 1729
 //! ```
 //!
+//! Trace natively without using [`strace`] (note that this requires Linux 5.3 or newer on
+//! `x86_64`, `aarch64` or `riscv64`):
+//! ```bash
+//! ftrace --backend ptrace -- ls
+//! ```
+//!
+//! Analyse the output of a previous `strace` run (pass `-` to read from STDIN). For best results it should be
+//! recorded with the same options that `ftrace` spawns `strace` with, which are listed under `--input` in the
+//! output of `ftrace --help`:
+//! ```bash
+//! strace <options from ftrace --help> -o trace.log -- ls
+//! ftrace --input trace.log --cwd /path/to/where/ls/ran
+//! ```
+//!
+//! ### Depfiles
+//!
+//! Use `--format depfile` to write a Make-compatible `.d` file to `--output` once the program has
+//! finished, whose prerequisites are the regular files that it successfully read. This can be used
+//! with `include` in a Makefile, or with `depfile =` in Ninja:
+//! ```bash
+//! ftrace --format depfile --target main.o --exclude-set system -o main.d -- cc -c main.c -o main.o
+//! ```
+//!
+//! Pass `--written-targets` to use the files that the program wrote as targets too (files that were
+//! written are never listed as prerequisites). Spaces, `#` and `$` in paths are escaped.
+//!
+//! ### AppArmor profiles
+//!
+//! Use `--format apparmor` to write a skeleton of an AppArmor profile for the traced program to
+//! `--output` once it has finished, with a rule for each path that it successfully accessed:
+//! ```bash
+//! ftrace --format apparmor --transition sendmail -o /etc/apparmor.d/usr.bin.python3 -- python3 app.py
+//! ```
+//!
+//! Three or more files in the same directory with the same permissions are collapsed into a single
+//! glob (e.g.: `/usr/lib/python3/*.py r,`), pids in `/proc` are replaced with `@{pid}`, and shared
+//! libraries are given the `m` permission. Programs that are executed inherit the profile (`ix`),
+//! unless they match `--transition`, in which case they need a profile of their own (`px`).
+//!
+//! The profile is only a starting point: review it before loading it, since a trace only covers the
+//! paths that were accessed during that run.
+//!
+//! ### Sandboxing with Landlock
+//!
+//! `ftrace record` traces a command and saves the paths that it read, wrote and executed to a policy
+//! file, which `ftrace enforce` then uses to run the command in a [Landlock] sandbox that denies
+//! every other access. Any access that's denied is printed as it happens:
+//! ```bash
+//! ftrace record --policy tool.toml -- ./tool --build
+//! ftrace enforce --policy tool.toml -- ./tool --build
+//! ```
+//!
+//! Every access is recorded, so options that filter the printed paths (such as `--type`,
+//! `--exclude-set` and `--relative-to`, or their defaults from a config file) are ignored.
+//!
+//! The policy is a TOML file with `read`, `write` and `execute` lists, which can be edited by hand.
+//! Some accesses are allowed more broadly than they were recorded:
+//!
+//! * paths that no longer exist (such as temporary files) are allowed beneath their nearest
+//!   existing directory,
+//! * writing a file also allows files to be created, removed and renamed in its directory,
+//! * executing a program also allows its interpreter (e.g.: `/lib64/ld-linux-x86-64.so.2`).
+//!
+//! Paths in the `/proc` directory of the process that accessed them are saved beneath `/proc/self`,
+//! since pids differ on each run. They're only allowed for the traced program itself rather than
+//! the processes it creates, and paths in the `/proc` directory of any other process are denied.
+//! `enforce` always uses the `ptrace` backend, and requires Linux 5.13 or newer.
+//!
+//! [Landlock]: https://docs.kernel.org/userspace-api/landlock.html
+//!
+//! ### Archiving the files a program read
+//!
+//! Use `--archive` to write every regular file that the program successfully read to a tarball once
+//! it has finished, so that the run can be reproduced elsewhere (e.g.: in a chroot):
+//! ```bash
+//! ftrace --archive env.tar -- /opt/tool/bin/tool --build
+//! mkdir root && tar -xf env.tar -C root && sudo chroot root /opt/tool/bin/tool --build
+//! ```
+//!
+//! Files are stored at their absolute paths with their modes, along with every symlink that was
+//! followed to reach them (e.g.: `/lib -> usr/lib`) and the interpreter of each program that was
+//! executed. The archive also contains a `.ftrace/missing` manifest of the paths that the program
+//! looked for but didn't exist (one quoted path per line), which are often worth checking when a
+//! program behaves differently elsewhere. Files in `/proc`, `/sys` and `/dev` aren't archived.
+//!
+//! ### Hashing the files a program used
+//!
+//! Use `--hash sha256` to write the digest of every regular file that the program successfully
+//! accessed to the file given by `--manifest` once it has finished, which records which version of
+//! each input a build used (and what it produced). The manifest is in the same format as
+//! `sha256sum`, so that it can be checked later:
+//! ```bash
+//! ftrace --hash sha256 --manifest build.sha256 --exclude-set system -- make
+//! sha256sum -c build.sha256
+//! ```
+//!
+//! Files that were read are hashed when they're first seen, and files that were written are hashed
+//! once the process that wrote them has exited. When reading a trace with `--input`, files are
+//! hashed as they are when `ftrace` runs, rather than when they were traced.
+//!
+//! ### Comparing traces
+//!
+//! `ftrace diff` compares two saved traces (either the output of `--format jsonl`, or the raw output
+//! of `strace`), which shows how a program's file accesses changed, e.g.: after upgrading a
+//! dependency:
+//! ```bash
+//! ftrace --format jsonl --non-existent -- ./tool > before.jsonl
+//! ftrace --format jsonl --non-existent -- ./tool > after.jsonl
+//! ftrace --exclude-set system diff --normalize all before.jsonl after.jsonl
+//! ```
+//!
+//! Paths that were only accessed in the second trace are printed with a `+`, those that were only
+//! accessed in the first with a `-`, and those whose access mode changed (e.g.: `r -> rw`) or that
+//! were found in one trace but missing in the other with a `~`. Use `--normalize` to ignore the
+//! parts of paths that differ on every run: `pids` in `/proc`, `timestamps`, random names in
+//! `temp-dirs`, or `all` of them. Like `diff(1)`, it exits with 1 if the traces differ.
+//!
+//! ### Configuration
+//!
+//! Default options can be set in `$XDG_CONFIG_HOME/ftrace/config.toml` (usually
+//! `~/.config/ftrace/config.toml`), and in a `.ftrace.toml` file in a project's directory (or any
+//! of its parents). Each key is the long name of an option, and named profiles can be selected with
+//! `--profile NAME`:
+//! ```toml
+//! no-duplicates = true
+//! color = true
+//! type = ["f"]
+//!
+//! [profile.build]
+//! exclude-set = ["system"]
+//! only-exe = ["cc1", "ld"]
+//! ```
+//!
+//! Options given on the command line take precedence over those from a profile, which take
+//! precedence over those from `.ftrace.toml`, which take precedence over those from
+//! `config.toml`. Note that flags which are enabled in a file can't be disabled on the command
+//! line, so it's best to put those in a profile.
+//!
+//! ### Quoting paths
+//!
+//! Paths are printed exactly as the program accessed them, even if they aren't valid UTF-8. By
+//! default each path is wrapped in double quotes with C-style escapes (e.g.: `"/tmp/caf\xe9"`),
+//! which can be changed with `--quote`:
+//!
+//! * `shell` only quotes paths that need it, so they can be pasted into a shell
+//!   (e.g.: `$'/tmp/caf\xe9'`),
+//! * `none` prints the raw bytes of each path,
+//! * `json` prints each path as a JSON string (bytes that aren't valid UTF-8 are replaced).
+//!
+//! To feed the paths to another program, use `--plain` to print one unquoted path per line, or
+//! `--print0` to separate them with NUL bytes instead (which works even if a path contains a
+//! newline). Since the program's own output is printed too, redirect it elsewhere:
+//! ```bash
+//! ftrace --print0 --no-duplicates --type f -- sh -c 'make > /dev/null' | tar --null -T - -czf inputs.tar.gz
+//! ftrace --plain --no-duplicates --type f -- sh -c 'make > /dev/null' | rsync --files-from=- / backup/
+//! ```
+//!
+//! ### JSON output
+//!
+//! Use `--format jsonl` to print one JSON object per line, which is easier to consume from other
+//! tools:
+//! ```bash
+//! ftrace --format jsonl -- ls
+//! ```
+//!
+//! Each object has the following fields:
+//!
+//! | Field          | Type              | Description                                                         |
+//! | -------------- | ----------------- | ------------------------------------------------------------------- |
+//! | `version`      | number            | The version of this schema (currently `1`)                          |
+//! | `path`         | string            | The path that was accessed (invalid UTF-8 is replaced with `U+FFFD`) |
+//! | `path_bytes`   | string            | The raw bytes of `path`, encoded as hexadecimal                     |
+//! | `syscall`      | string            | The name of the syscall, e.g.: `openat`                             |
+//! | `pid`          | number or `null`  | The pid of the process that made the syscall, if known              |
+//! | `timestamp_us` | number or `null`  | When the syscall was made, in microseconds since the Unix epoch     |
+//! | `result`       | number or `null`  | The value returned by the syscall, if known (e.g.: `exit` never returns) |
+//! | `errno`        | string or `null`  | The error if the syscall failed, e.g.: `ENOENT (No such file or directory)` |
+//! | `duration_us`  | number or `null`  | How long the syscall took in microseconds, if it was recorded (with `strace -T`) |
+//! | `success`      | boolean or `null` | Whether the syscall succeeded, if that can be determined            |
+//! | `mode`         | string            | The access mode: `r`, `w`, `rw` or `?`                              |
+//!
+//! The `version` is incremented whenever a field is changed or removed, but not when new fields
+//! are added.
+//!
 //! ### Caveats
 //!
-//! Since [`strace`] outputs via STDERR, if the program being run also emits output over STDERR it
-//! can confuse `ftrace`. For this reason any line that `ftrace` doesn't recognise is ignored and not
-//! parsed. You can print lines that weren't recognised with the `--invalid` flag.
+//! The traced program's STDIN, STDOUT and STDERR are passed through untouched (so interactive
+//! programs can be traced too), which means anything it prints to STDOUT is interleaved with the
+//! paths that `ftrace` prints. Redirect the program's output within the command if that's a
+//! problem, e.g.: `ftrace -- sh -c 'make > /dev/null'`.
+//!
+//! When reading a trace with `--input`, any line that `ftrace` doesn't recognise is ignored. You
+//! can print lines that weren't recognised with the `--invalid` flag.
+//!
+//! ## Library
+//!
+//! `ftrace` can also be used as a library, which streams each access as a typed `FileAccess`:
+//! ```rust
+//! use ftrace::{Event, Tracer};
+//!
+//! let mut trace = Tracer::command(&["ls"]).no_duplicates(true).spawn()?;
+//! for event in &mut trace {
+//!     if let Event::Access(access) = event {
+//!         println!("{} {}", access.mode, access.path.display());
+//!     }
+//! }
+//! trace.wait()?;
+//! ```
 //!
 //! # Installation
 //!
-//! First and foremost, make sure you've installed [`strace`] on your system.
+//! It's recommended to install [`strace`] on your system, although it's not required since `ftrace`
+//! falls back to its native `ptrace` backend if [`strace`] can't be found.
 //! It's almost always in your distribution's package manager.
 //!
 //! ### Precompiled binaries
@@ -68,28 +315,38 @@
 //! [`strace`]: https://strace.io/
 //! [tracefile]: https://gitlab.com/ole.tange/tangetools/tree/master/tracefile
 
-mod analysis;
+mod apparmor;
+mod archive;
 mod cli;
-mod fs;
+mod config;
+mod diff;
 mod macros;
-mod parse;
+mod manifest;
+mod output;
 
-use std::collections::HashSet;
-use std::io::{BufRead, BufReader, Write};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::sync::{Arc, Mutex};
 use std::{env, process};
 
 use anyhow::Result;
 use clap::crate_name;
 use flexi_logger::{opt_format, Logger};
-use termcolor::{Color, ColorChoice, WriteColor};
-use which::which;
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
-use parse::{string::decode_hex, StraceLine, StraceToken};
-
-// TODO: support strace's file descriptor decoding? (--decode-fds=all|-yy)
+use apparmor::Profile;
+use archive::Archive;
+use cli::Command;
+use diff::Summary;
+use ftrace::errno::Errno;
+use ftrace::parse::CallResult;
+use ftrace::policy::Policy;
+use ftrace::tree::{ProcessNode, ProcessTree};
+use ftrace::{Event, FileAccess, Tracer};
+use manifest::Manifest;
+use output::{Depfile, Format, GroupBy};
 
 fn init_logging() -> Result<PathBuf> {
     let log_dir = env::temp_dir().join(format!(".{}", crate_name!()));
@@ -104,6 +361,99 @@ fn init_logging() -> Result<PathBuf> {
     Ok(log_dir)
 }
 
+fn print_access(args: &cli::Args, access: &FileAccess, indent: &str) {
+    let color = match access.success {
+        Some(true) => Color::Green,
+        Some(false) => Color::Yellow,
+        None => Color::White,
+    };
+
+    let s = access.path.to_string_lossy();
+    match args.format {
+        Format::Text => {
+            let mut line = indent.as_bytes().to_vec();
+            if args.show_mode {
+                line.extend(format!("{:2} ", access.mode).as_bytes());
+            }
+            line.extend(args.quote.quote(&access.path));
+            line.push(if args.print0 { b'\0' } else { b'\n' });
+
+            // NOTE: paths are written as bytes, since they may not be valid UTF-8
+            let mut stdout = StandardStream::stdout(choice!(args.color));
+            stdout
+                .set_color(ColorSpec::new().set_fg(Some(color)))
+                .unwrap();
+            stdout.write_all(&line).unwrap();
+        }
+        Format::Jsonl => {
+            let event = output::Event {
+                version: output::SCHEMA_VERSION,
+                path: Cow::Borrowed(&s),
+                path_bytes: hex::encode(access.path.as_os_str().as_bytes()),
+                syscall: &access.syscall,
+                pid: access.pid,
+                timestamp_us: access.time.map(|time| time.as_micros()),
+                result: access.result.value(),
+                errno: match access.result {
+                    CallResult::Error(errno) => Some(errno.to_string()),
+                    _ => None,
+                },
+                duration_us: access.duration.map(|duration| duration.as_micros()),
+                success: access.success,
+                mode: access.mode.to_string(),
+            };
+            p!(false, None, "{}", serde_json::to_string(&event).unwrap());
+        }
+        Format::Depfile | Format::Apparmor => {
+            unreachable!("{:?} is written once tracing has finished", args.format)
+        }
+    }
+}
+
+/// Describes a process by its pid and the program it was running, e.g.: `42 make -C src`.
+fn describe(node: &ProcessNode) -> String {
+    let pid = node.pid.map(|pid| pid.to_string());
+    format!(
+        "{} {}",
+        pid.as_deref().unwrap_or("?"),
+        node.command().as_deref().unwrap_or("?")
+    )
+}
+
+/// Prints a process and its children, along with how many paths each accessed and how it exited.
+fn print_tree(args: &cli::Args, tree: &ProcessTree, i: usize, prefix: &str, last: Option<bool>) {
+    let node = &tree.nodes()[i];
+    let (branch, indent) = match last {
+        None => ("", ""),
+        Some(false) => ("├── ", "│   "),
+        Some(true) => ("└── ", "    "),
+    };
+    let exit = node.exit.map(|exit| format!(", {}", exit));
+    let count = node.accesses.len();
+    p!(
+        args.color,
+        None,
+        "{}{}{} ({} {}{})",
+        prefix,
+        branch,
+        describe(node),
+        count,
+        if count == 1 { "path" } else { "paths" },
+        exit.unwrap_or_default()
+    );
+
+    let prefix = format!("{}{}", prefix, indent);
+    for (n, child) in node.children.iter().enumerate() {
+        print_tree(
+            args,
+            tree,
+            *child,
+            &prefix,
+            Some(n + 1 == node.children.len()),
+        );
+    }
+}
+
 fn main() -> Result<()> {
     let log_dir = match init_logging() {
         Ok(dir) => dir,
@@ -122,188 +472,236 @@ fn main() -> Result<()> {
         }};
     }
 
-    let strace_path = match which("strace") {
-        Ok(path) => path,
-        Err(e) => exit_with_error!("Failed to find `strace` binary: {}", e),
-    };
+    // exit quietly when the output is piped to a program that exits early (e.g.: `head`) rather than panicking, which
+    // also restores the default behaviour for the traced program
+    unsafe { libc::signal(libc::SIGPIPE, libc::SIG_DFL) };
 
     let app_args = cli::Args::parse();
     log::trace!("{:?}", app_args);
 
+    if let Some(Command::Diff { a, b, normalize }) = &app_args.command {
+        let filter = app_args.path_filter();
+        let result = Summary::load(a, &filter, normalize).and_then(|a| {
+            let b = Summary::load(b, &filter, normalize)?;
+            Ok(diff::write(&a, &b, app_args.quote, app_args.color)?)
+        });
+        match result {
+            // exit like `diff(1)`, so scripts can check whether anything changed
+            Ok(differs) => process::exit(differs as i32),
+            Err(e) => {
+                log::error!("{:#}", e);
+                p!(false, None, "{:#}", e);
+                process::exit(2);
+            }
+        }
+    }
+
     // BUG: there's a bug with clap right now which means we have to manually check for this case
-    if app_args.pid.is_none() && app_args.cmd.is_empty() {
+    if app_args.pid.is_none() && app_args.input.is_none() && app_args.cmd.is_empty() {
         use clap::IntoApp;
         cli::Args::into_app().print_help().unwrap();
-        exit_with_error!("No command or pid given!");
+        exit_with_error!("No command, pid or input given!");
     }
 
-    let mut child = Command::new(strace_path)
-        // follow and trace the process's forks
-        .arg("--follow-forks")
-        // monitor all statuses: even though this is almost the same as the default behaviour, by specifying this
-        // `strace` will wait for each syscall to end before printing it. This means that we don't have to parse and
-        // deal with `<unfinished... >` and `<... resume XXX>` logs
-        .arg("--status=successful,failed,unfinished,unavailable,detached")
-        // include timestamps with microsecond precision
-        .arg("-ttt")
-        // print all strings with hexadecimal escapes
-        .arg("--strings-in-hex")
-        // only trace file syscalls since that's what we're interested in
-        .arg("--trace=%file")
-        // as from `man strace`: Use this option to get all of the gory details
-        .arg("--no-abbrev")
-        // the user-provided command
-        .args(&app_args.cmd)
-        // the user-provided pid
-        .args(
-            &app_args
-                .pid
-                .map(|pid| vec![format!("--attach={}", pid)])
-                .unwrap_or(vec![]),
-        )
-        // `strace` logs via stderr
-        // NOTE: if the spawned/attached process also logs via stderr then we'll see that data too
-        .stderr(Stdio::piped())
-        // ignore the command's stderr
-        .stdout(Stdio::null())
-        .spawn()?;
-
-    let reader = BufReader::new(child.stderr.as_mut().unwrap());
-    let seen_values = Arc::new(Mutex::new(HashSet::new()));
-    for line in reader.lines() {
-        let line = line?;
-        log::trace!("RAW LINE: {}", line);
-        match StraceLine::from_str(&line) {
-            Ok(strace) => {
-                log::debug!("PARSED LINE: {}", strace);
-                if let StraceToken::PermissionDenied(pid) = strace.inner {
-                    p!(
-                        app_args.color,
-                        Color::Yellow,
-                        "{}\n{}",
-                        format!("Could not attach to pid: {}, permission denied.", pid),
-                        "Try re-running the command with elevated permissons."
-                    );
-                    break;
-                }
-
-                let app_args = &app_args;
-                let file_types = app_args.file_types();
-                let seen_values = &seen_values;
-                strace.walk(&move |token| {
-                    if let StraceToken::Call {
-                        name, result, args, ..
-                    } = token
-                    {
-                        // call expressions without results are inline call expressions, so skip them
-                        let result = match result {
-                            Some(result) => result,
-                            None => return true,
-                        };
-
-                        let fn_info = &analysis::FN_MAP[name];
-                        let color = match fn_info.did_succeed(*result) {
-                            Some(true) => Color::Green,
-                            Some(false) => {
-                                if app_args.non_existent {
-                                    Color::Yellow
-                                } else {
-                                    return true;
-                                }
-                            }
-                            None => Color::White,
-                        };
-
-                        // NOTE: handle special case for `execve`: the first argument is the binary being executed, and
-                        // the second argument is the binary's `argv` (which does not contain paths for file accesses)
-                        let maybe_paths = if *name == "execve" {
-                            if let StraceToken::String(s) = &args[0] {
-                                vec![*s]
-                            } else {
-                                vec![]
-                            }
-                        } else {
-                            token.strs()
-                        };
-
-                        for s in maybe_paths {
-                            let s = decode_hex(s);
-                            if let Some(file_types) = file_types {
-                                let path = Path::new(&s);
-                                match path.metadata() {
-                                    Ok(meta) => {
-                                        let ft = meta.file_type();
-                                        if (file_types.files && !fs::is_file(&path))
-                                            || (file_types.directories && !fs::is_dir(&path))
-                                            || (file_types.symlinks && !fs::is_symlink(&path))
-                                            || (file_types.sockets && !fs::is_socket(&ft))
-                                            || (file_types.pipes && !fs::is_pipe(&ft))
-                                            || (file_types.executables && !fs::is_executable(&meta))
-                                            || (file_types.empty && !fs::is_empty(&path))
-                                        {
-                                            continue;
-                                        }
-                                    }
-                                    // NOTE: skip here because the string was probably not a valid path?
-                                    Err(_) => continue,
-                                }
-                            }
-
-                            if s.is_empty() {
-                                continue;
-                            }
-
-                            // Skip duplicates if set
-                            if app_args.no_duplicates {
-                                if seen_values.lock().unwrap().contains(&s) {
-                                    continue;
-                                }
+    let recording = matches!(app_args.command, Some(Command::Record { .. }));
+    let mut tracer = match (&app_args.input, app_args.pid) {
+        // replay existing output rather than tracing anything
+        (Some(input), _) if input == Path::new("-") => Tracer::input(BufReader::new(io::stdin())),
+        (Some(input), _) => match File::open(input) {
+            Ok(file) => Tracer::input(BufReader::new(file)),
+            Err(e) => exit_with_error!("Failed to open {}: {}", input.display(), e),
+        },
+        (None, Some(pid)) => Tracer::pid(pid),
+        (None, None) => Tracer::command(&app_args.cmd),
+    }
+    // the paths that didn't exist are needed for the archive's manifest, even if they aren't printed
+    .non_existent(app_args.non_existent || app_args.archive.is_some())
+    .no_duplicates(app_args.no_duplicates);
 
-                                seen_values.lock().unwrap().insert(s.clone());
-                            }
+    // a policy has to allow every access that the program makes, so nothing is filtered out of it (even by the
+    // defaults from the config file)
+    if !recording {
+        tracer = tracer
+            .only_pids(app_args.only_pids.iter().copied())
+            .only_exes(app_args.only_exes.iter().cloned())
+            .exclude_exes(app_args.exclude_exes.iter().cloned())
+            .descendants(app_args.descendants)
+            .include(app_args.include.iter().cloned())
+            .exclude(app_args.exclude.iter().cloned())
+            .regexes(app_args.regexes.iter().cloned());
+        for set in &app_args.exclude_sets {
+            tracer = tracer.exclude_set(*set);
+        }
+        if let Some(dir) = &app_args.relative_to {
+            tracer = tracer.relative_to(dir);
+        }
+        if let Some(file_types) = app_args.file_types() {
+            tracer = tracer.file_types(file_types.clone());
+        }
+        if let Some(access_modes) = app_args.access_modes() {
+            tracer = tracer.access_modes(access_modes.clone());
+        }
+    }
 
-                            p!(app_args.color, color, "{:?}", s);
-                        }
+    if let Some(backend) = app_args.backend {
+        tracer = tracer.backend(backend);
+    }
+    if let Some(cwd) = &app_args.cwd {
+        tracer = tracer.cwd(cwd);
+    }
+    if !app_args.errnos.is_empty() {
+        tracer = tracer.errnos(app_args.errnos.iter().copied());
+    }
+    if let Some(Command::Enforce { policy, .. }) = &app_args.command {
+        let ruleset = match Policy::load(policy).and_then(|policy| policy.ruleset()) {
+            Ok(ruleset) => ruleset,
+            Err(e) => exit_with_error!("{:#}", e),
+        };
+        tracer = tracer.sandbox(ruleset);
+        // Landlock denies accesses with `EACCES`
+        if app_args.errnos.is_empty() {
+            tracer = tracer.errnos([Errno(libc::EACCES)]);
+        }
+    }
+    let mut trace = match tracer.spawn() {
+        Ok(trace) => trace,
+        Err(e) => exit_with_error!("{:#}", e),
+    };
 
-                        false
-                    } else {
-                        true
+    // grouped output is printed once tracing has finished
+    let mut tree = ProcessTree::default();
+    let mut depfile = Depfile::default();
+    let mut profile = Profile::default();
+    let mut policy = Policy::default();
+    let mut archive = Archive::default();
+    let mut manifest = app_args
+        .hash
+        .map(|algorithm| Manifest::new(algorithm, app_args.relative_to.clone()));
+    let buffered = app_args.group_by.is_some() || app_args.tree;
+    for event in &mut trace {
+        if app_args.archive.is_some() {
+            match &event {
+                Event::Access(access) => {
+                    archive.add(access);
+                    // failed accesses are only traced for the archive, unless they were asked for
+                    if access.success == Some(false)
+                        && !app_args.non_existent
+                        && app_args.errnos.is_empty()
+                    {
+                        continue;
                     }
-                });
+                }
+                Event::Exec { path, .. } => archive.exec(path),
+                _ => {}
             }
+        }
+        if let Some(manifest) = &mut manifest {
+            match &event {
+                Event::Access(access) => manifest.add(access),
+                Event::Exit { pid, .. } => manifest.exit(*pid),
+                _ => {}
+            }
+        }
+        if buffered {
+            tree.update(&event);
+        }
+
+        match event {
+            Event::Access(access) if recording => policy.add(&access),
+            Event::Access(_) if buffered => {}
+            Event::Access(access) if app_args.format == Format::Depfile => depfile.add(&access),
+            Event::Access(access) if app_args.format == Format::Apparmor => profile.add(&access),
+            Event::Access(access) => print_access(&app_args, &access, ""),
             #[allow(unused)]
-            Err(e) => {
-                log::warn!("INVALID LINE: {}", line);
+            Event::InvalidLine { line, error } => {
                 if app_args.invalid_lines {
                     #[cfg(not(debug_assertions))]
                     p!(app_args.color, Color::Red, "PARSE_ERR: {}", line);
                     #[cfg(debug_assertions)]
-                    p!(app_args.color, Color::Red, "{}", e);
+                    p!(app_args.color, Color::Red, "{}", error);
                 }
             }
+            Event::PermissionDenied(pid) => {
+                p!(
+                    app_args.color,
+                    Color::Yellow,
+                    "{}\n{}",
+                    format!("Could not attach to pid: {}, permission denied.", pid),
+                    "Try re-running the command with elevated permissons."
+                );
+            }
+            Event::Exec { path, .. } if recording => policy.exec(&path),
+            Event::Exec { path, argv, .. } if app_args.format == Format::Apparmor => {
+                profile.exec(&path, &argv, &app_args.transitions)
+            }
+            Event::Spawn { .. } | Event::Exec { .. } | Event::Exit { .. } => {}
         }
     }
 
-    p!(app_args.color, None);
-
-    match child.wait() {
-        Ok(exit_status) => {
-            let msg = format!(
-                "strace exited with code: {}",
-                exit_status
-                    .code()
-                    .map(|c| c.to_string())
-                    .unwrap_or("???".to_string())
-            );
-
-            if !exit_status.success() {
-                exit_with_error!("{}", msg);
-            } else {
-                log::trace!("{}", msg);
+    if let Some(GroupBy::Process) = app_args.group_by {
+        for node in tree.nodes().iter().filter(|node| !node.accesses.is_empty()) {
+            p!(app_args.color, Color::Cyan, "{}", describe(node));
+            for access in &node.accesses {
+                print_access(&app_args, access, "  ");
             }
         }
-        Err(e) => exit_with_error!("An error occurred while waiting for process to end: {}", e),
     }
+    if app_args.tree {
+        for root in tree.roots() {
+            print_tree(&app_args, &tree, root, "", None);
+        }
+    }
+    if let Some(Command::Record { policy: path, .. }) = &app_args.command {
+        let mut comment = String::from("Recorded by ftrace");
+        if !app_args.cmd.is_empty() {
+            comment = format!("{} from: {}", comment, app_args.cmd.join(" "));
+        }
+        if let Err(e) = policy.save(path, &comment) {
+            exit_with_error!("{:#}", e);
+        }
+    }
+    if let (Format::Depfile, Some(path)) = (app_args.format, &app_args.output) {
+        let result = File::create(path).and_then(|file| {
+            let mut w = BufWriter::new(file);
+            depfile.write(&mut w, &app_args.targets, app_args.written_targets)?;
+            w.flush()
+        });
+        if let Err(e) = result {
+            exit_with_error!("Failed to write depfile {}: {}", path.display(), e);
+        }
+    }
+    if let (Format::Apparmor, Some(path)) = (app_args.format, &app_args.output) {
+        let result = File::create(path).and_then(|file| {
+            let mut w = BufWriter::new(file);
+            profile.write(&mut w)?;
+            w.flush()
+        });
+        if let Err(e) = result {
+            exit_with_error!("Failed to write AppArmor profile {}: {}", path.display(), e);
+        }
+    }
+    if let Some(path) = &app_args.archive {
+        if let Err(e) = File::create(path).and_then(|file| archive.write(BufWriter::new(file))) {
+            exit_with_error!("Failed to write archive {}: {}", path.display(), e);
+        }
+    }
+    if let (Some(manifest), Some(path)) = (&mut manifest, &app_args.manifest) {
+        let result = File::create(path).and_then(|file| {
+            let mut w = BufWriter::new(file);
+            manifest.write(&mut w)?;
+            w.flush()
+        });
+        if let Err(e) = result {
+            exit_with_error!("Failed to write manifest {}: {}", path.display(), e);
+        }
+    }
+
+    p!(app_args.color, None);
 
-    Ok(())
+    match trace.wait() {
+        // exit the same way as the traced program, so this can be used as a wrapper in scripts
+        Ok(Some(exit)) if app_args.input.is_none() => process::exit(exit.code()),
+        Ok(_) => Ok(()),
+        Err(e) => exit_with_error!("{:#}", e),
+    }
 }
@@ -0,0 +1,196 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use clap::ArgEnum;
+use sha2::{Digest, Sha256};
+
+use ftrace::FileAccess;
+
+/// The algorithm used to hash the contents of files with `--hash`.
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+pub enum HashAlgorithm {
+    Sha256,
+}
+
+impl HashAlgorithm {
+    /// Returns the hexadecimal digest of a file's contents, or `None` if it isn't a regular file.
+    fn digest(self, path: &Path) -> io::Result<Option<String>> {
+        if !fs::metadata(path)?.is_file() {
+            return Ok(None);
+        }
+
+        let mut file = File::open(path)?;
+        match self {
+            HashAlgorithm::Sha256 => {
+                let mut hasher = Sha256::new();
+                io::copy(&mut file, &mut hasher)?;
+                Ok(Some(hex::encode(hasher.finalize())))
+            }
+        }
+    }
+}
+
+/// The digests of the regular files that were accessed, as they're written with `--hash`.
+#[derive(Debug)]
+pub struct Manifest {
+    algorithm: HashAlgorithm,
+    /// The directory that relative paths are relative to (i.e.: --relative-to)
+    base: Option<PathBuf>,
+    digests: BTreeMap<PathBuf, String>,
+    /// The paths that each process wrote, which are hashed once it has exited (and so finished writing them)
+    written: HashMap<Option<i32>, BTreeSet<PathBuf>>,
+}
+
+impl Manifest {
+    pub fn new(algorithm: HashAlgorithm, base: Option<PathBuf>) -> Manifest {
+        Manifest {
+            algorithm,
+            base,
+            digests: BTreeMap::new(),
+            written: HashMap::new(),
+        }
+    }
+
+    /// Adds a path that was accessed, ignoring any access that failed. Files that were read are hashed the first time
+    /// they're seen, before the program has a chance to change them.
+    pub fn add(&mut self, access: &FileAccess) {
+        if access.success != Some(true) {
+            return;
+        }
+
+        if access.mode.is_write() {
+            self.written
+                .entry(access.pid)
+                .or_default()
+                .insert(access.path.clone());
+        } else if access.mode.is_read() && !self.digests.contains_key(&access.path) {
+            self.hash(&access.path);
+        }
+    }
+
+    /// Hashes the files that a process wrote, since it has exited.
+    pub fn exit(&mut self, pid: Option<i32>) {
+        for path in self.written.remove(&pid).unwrap_or_default() {
+            self.hash(&path);
+        }
+    }
+
+    fn hash(&mut self, path: &Path) {
+        let absolute = match &self.base {
+            Some(base) => base.join(path),
+            None => path.to_path_buf(),
+        };
+        match self.algorithm.digest(&absolute) {
+            Ok(Some(digest)) => {
+                self.digests.insert(path.to_path_buf(), digest);
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("failed to hash {}: {}", absolute.display(), e),
+        }
+    }
+
+    /// Writes the manifest in the same format as `sha256sum`, so it can be checked with `sha256sum -c`. Files written
+    /// by processes that weren't seen to exit are hashed now.
+    pub fn write(&mut self, w: &mut impl Write) -> io::Result<()> {
+        let pids = self.written.keys().copied().collect::<Vec<_>>();
+        for pid in pids {
+            self.exit(pid);
+        }
+
+        for (path, digest) in &self.digests {
+            let path = path.as_os_str().as_bytes();
+            // NOTE: like `sha256sum`, a line starts with `\` if its path had to be escaped
+            if path.iter().any(|b| matches!(b, b'\\' | b'\n' | b'\r')) {
+                w.write_all(b"\\")?;
+            }
+            write!(w, "{}  ", digest)?;
+            for b in path {
+                match b {
+                    b'\\' => w.write_all(b"\\\\")?,
+                    b'\n' => w.write_all(b"\\n")?,
+                    b'\r' => w.write_all(b"\\r")?,
+                    b => w.write_all(&[*b])?,
+                }
+            }
+            w.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use ftrace::analysis::AccessMode;
+    use ftrace::parse::CallResult;
+    use ftrace::FileAccess;
+
+    use super::{HashAlgorithm, Manifest};
+
+    const A: &str = "87428fc522803d31065e7bce3cf03fe475096631e5e07bbd7a0fde60c4cf25c7";
+    const B: &str = "0263829989b6fd954f72baaf2fc64bc2e2f01d692d4de72986ea808f6e99813f";
+
+    fn access(path: &Path, pid: i32, mode: AccessMode) -> FileAccess {
+        FileAccess {
+            path: path.to_path_buf(),
+            syscall: "openat".to_string(),
+            pid: Some(pid),
+            time: None,
+            result: CallResult::Int(3),
+            info: None,
+            duration: None,
+            success: Some(true),
+            mode,
+        }
+    }
+
+    fn write(manifest: &mut Manifest) -> String {
+        let mut output = vec![];
+        manifest.write(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn hash_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let (read, written) = (dir.path().join("read.txt"), dir.path().join("written.txt"));
+        fs::write(&read, "a\n").unwrap();
+        fs::write(&written, "a\n").unwrap();
+
+        let mut manifest = Manifest::new(HashAlgorithm::Sha256, Some(dir.path().to_path_buf()));
+        // files that are read are hashed before they change, and written files once their process exits
+        manifest.add(&access(Path::new("read.txt"), 1, AccessMode::Read));
+        manifest.add(&access(Path::new("written.txt"), 1, AccessMode::Write));
+        manifest.add(&access(dir.path(), 1, AccessMode::Read));
+        fs::write(&read, "b\n").unwrap();
+        fs::write(&written, "b\n").unwrap();
+        manifest.add(&access(Path::new("read.txt"), 1, AccessMode::Read));
+
+        assert_eq!(
+            write(&mut manifest),
+            format!("{}  read.txt\n{}  written.txt\n", A, B)
+        );
+    }
+
+    #[test]
+    fn escape_paths() {
+        let mut manifest = Manifest::new(HashAlgorithm::Sha256, None);
+        for path in ["/a b", "/a\\b", "/a\nb", "/a\rb"] {
+            manifest.digests.insert(PathBuf::from(path), A.to_string());
+        }
+
+        assert_eq!(
+            write(&mut manifest),
+            format!(
+                "\\{0}  /a\\nb\n\\{0}  /a\\rb\n{0}  /a b\n\\{0}  /a\\\\b\n",
+                A
+            )
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use clap::ArgEnum;
+use serde::Serialize;
+
+use ftrace::FileAccess;
+
+/// The version of the JSON Lines schema, which is incremented whenever a field is changed or removed.
+/// Adding a new field doesn't change the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+pub enum Format {
+    /// One quoted path per line
+    Text,
+    /// One JSON object per line (see the README for the schema)
+    Jsonl,
+    /// A Make-compatible `.d` file, written once tracing has finished
+    Depfile,
+    /// An AppArmor profile skeleton, written once tracing has finished
+    Apparmor,
+}
+
+/// How paths are quoted in the `text` format.
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+pub enum Quote {
+    /// Double quotes, with C-style escapes for special characters and bytes that aren't valid UTF-8
+    C,
+    /// Quoted only if needed, so that the path can be pasted into a shell
+    Shell,
+    /// The raw bytes of the path
+    None,
+    /// A JSON string (bytes that aren't valid UTF-8 are replaced with U+FFFD)
+    Json,
+}
+
+impl Quote {
+    pub fn quote(self, path: &Path) -> Vec<u8> {
+        let bytes = path.as_os_str().as_bytes();
+        match self {
+            Quote::C => [&b"\""[..], &escape_c(bytes, b'"'), b"\""].concat(),
+            Quote::Shell => quote_shell(bytes),
+            Quote::None => bytes.to_vec(),
+            Quote::Json => serde_json::to_vec(&path.to_string_lossy()).unwrap(),
+        }
+    }
+}
+
+/// Escapes special characters and invalid UTF-8 with backslashes, as C (and `$'...'` in shells) would.
+fn escape_c(bytes: &[u8], quote: u8) -> Vec<u8> {
+    let mut escaped = vec![];
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let (valid, invalid) = match std::str::from_utf8(rest) {
+            Ok(valid) => (valid, &[][..]),
+            Err(e) => {
+                let (valid, invalid) = rest.split_at(e.valid_up_to());
+                let len = e.error_len().unwrap_or(invalid.len());
+                // NOTE: the prefix was just checked to be valid
+                (std::str::from_utf8(valid).unwrap(), &invalid[..len])
+            }
+        };
+        for c in valid.chars() {
+            match c {
+                '\\' => escaped.extend(b"\\\\"),
+                '\n' => escaped.extend(b"\\n"),
+                '\r' => escaped.extend(b"\\r"),
+                '\t' => escaped.extend(b"\\t"),
+                c if c as u32 == quote as u32 => escaped.extend(&[b'\\', quote]),
+                c if c.is_control() => {
+                    for b in c.encode_utf8(&mut [0; 4]).bytes() {
+                        escaped.extend(format!("\\x{:02x}", b).as_bytes());
+                    }
+                }
+                c => escaped.extend(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+        for b in invalid {
+            escaped.extend(format!("\\x{:02x}", b).as_bytes());
+        }
+        rest = &rest[valid.len() + invalid.len()..];
+    }
+
+    escaped
+}
+
+/// Quotes a path for a POSIX shell: safe paths are left as they are, others are wrapped in single quotes, and paths
+/// with special characters (or that aren't valid UTF-8) use `$'...'`.
+fn quote_shell(bytes: &[u8]) -> Vec<u8> {
+    let is_safe = |b: &u8| b.is_ascii_alphanumeric() || b"_@%+=:,./-".contains(b);
+    if !bytes.is_empty() && bytes.iter().all(is_safe) {
+        return bytes.to_vec();
+    }
+
+    let is_printable = std::str::from_utf8(bytes)
+        .map(|s| !s.chars().any(char::is_control))
+        .unwrap_or(false);
+    if is_printable {
+        let mut quoted = vec![b'\''];
+        for b in bytes {
+            match b {
+                b'\'' => quoted.extend(b"'\\''"),
+                b => quoted.push(*b),
+            }
+        }
+        quoted.push(b'\'');
+        quoted
+    } else {
+        [&b"$'"[..], &escape_c(bytes, b'\''), b"'"].concat()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+pub enum GroupBy {
+    /// The process that accessed each path, along with the program it was running
+    Process,
+}
+
+/// A single access of a path, as it's output with `--format jsonl`.
+#[derive(Debug, Serialize)]
+pub struct Event<'a> {
+    /// The version of the schema, see `SCHEMA_VERSION`
+    pub version: u32,
+    /// The path that was accessed (invalid UTF-8 is replaced with U+FFFD)
+    pub path: Cow<'a, str>,
+    /// The raw bytes of `path`, encoded as hexadecimal
+    pub path_bytes: String,
+    /// The name of the syscall
+    pub syscall: &'a str,
+    /// The pid of the process that made the syscall, if it's known
+    pub pid: Option<i32>,
+    /// When the syscall was made, in microseconds since the Unix epoch
+    pub timestamp_us: Option<u128>,
+    /// The value returned by the syscall, if it's known
+    pub result: Option<i64>,
+    /// The error returned by the syscall, e.g.: "ENOENT (No such file or directory)"
+    pub errno: Option<String>,
+    /// How long the syscall took in microseconds, if it was recorded
+    pub duration_us: Option<u128>,
+    /// Whether the syscall succeeded, if that can be determined
+    pub success: Option<bool>,
+    /// The access mode: "r", "w", "rw" or "?"
+    pub mode: String,
+}
+
+/// The files that a command read and wrote, as they're output with `--format depfile`.
+#[derive(Debug, Default)]
+pub struct Depfile {
+    /// Files that were read but not written, in the order they were first read
+    inputs: Vec<PathBuf>,
+    /// Files that were written, in the order they were first written
+    outputs: Vec<PathBuf>,
+    read: HashSet<PathBuf>,
+    written: HashSet<PathBuf>,
+}
+
+impl Depfile {
+    /// Adds a file that was accessed, ignoring any access that failed.
+    pub fn add(&mut self, access: &FileAccess) {
+        if access.success != Some(true) {
+            return;
+        }
+
+        if access.mode.is_write() && self.written.insert(access.path.clone()) {
+            self.outputs.push(access.path.clone());
+        }
+        if access.mode.is_read() && self.read.insert(access.path.clone()) {
+            self.inputs.push(access.path.clone());
+        }
+    }
+
+    /// Writes a rule with the given targets (and optionally the written files), whose prerequisites are the files that
+    /// were read. Files that were written are never prerequisites, since they're outputs of the command.
+    pub fn write(&self, w: &mut impl Write, targets: &[PathBuf], written: bool) -> io::Result<()> {
+        let written = self.outputs.iter().filter(|_| written);
+        let targets = targets.iter().chain(written).collect::<Vec<_>>();
+        if targets.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no files were written to use as targets",
+            ));
+        }
+        for (i, target) in targets.iter().enumerate() {
+            if i > 0 {
+                w.write_all(b" ")?;
+            }
+            w.write_all(&escape(target))?;
+        }
+        w.write_all(b":")?;
+
+        let inputs = self
+            .inputs
+            .iter()
+            .filter(|path| !self.written.contains(*path) && !targets.contains(path));
+        for input in inputs {
+            w.write_all(b" \\\n  ")?;
+            w.write_all(&escape(input))?;
+        }
+
+        w.write_all(b"\n")
+    }
+}
+
+/// Escapes a path so it's read as a single word by both Make and Ninja.
+fn escape(path: &Path) -> Vec<u8> {
+    let bytes = path.as_os_str().as_bytes();
+    let mut escaped = vec![];
+    for (i, b) in bytes.iter().enumerate() {
+        match b {
+            b' ' | b'#' | b':' => {
+                // like `gcc -MD`, the backslashes before an escaped character are escaped too
+                let backslashes = bytes[..i].iter().rev().take_while(|b| **b == b'\\');
+                escaped.extend(backslashes.map(|_| b'\\'));
+                escaped.extend(&[b'\\', *b]);
+            }
+            b'$' => escaped.extend(b"$$"),
+            b => escaped.push(*b),
+        }
+    }
+
+    escaped
+}
@@ -0,0 +1,111 @@
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+use pest::iterators::Pair;
+
+use crate::errno::Errno;
+use crate::parse::strace_token::StraceToken;
+use crate::parse::timestamp::decode_timestamp;
+use crate::parse::Rule;
+
+/// The value returned by a syscall.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CallResult {
+    /// A signed integer, such as a file descriptor or a number of bytes
+    Int(i64),
+    /// A value printed in hexadecimal, such as an address returned by `mmap`
+    Hex(u64),
+    /// The value isn't known, such as for `exit_group` which never returns
+    Unknown,
+    /// The call failed with the given `errno` (which `strace` prints as `-1` followed by its name)
+    Error(Errno),
+}
+
+impl CallResult {
+    /// Parses a result, along with the name of the error that may be printed after it.
+    pub fn from_pest(pair: Pair<Rule>, errno: Option<&str>) -> CallResult {
+        match pair.as_rule() {
+            Rule::unknown => CallResult::Unknown,
+            Rule::number if pair.as_str().starts_with("0x") => {
+                // NOTE: this can't overflow since the number was already parsed as an unsigned integer
+                CallResult::Hex(StraceToken::from_pest(pair).number().unwrap() as u64)
+            }
+            _ => {
+                let n = StraceToken::from_pest(pair).number().unwrap();
+                match errno.and_then(Errno::from_name) {
+                    Some(errno) if n < 0 => CallResult::Error(errno),
+                    _ => CallResult::Int(n),
+                }
+            }
+        }
+    }
+
+    /// Returns the value as a signed integer, if it's known.
+    pub fn value(&self) -> Option<i64> {
+        match self {
+            CallResult::Int(n) => Some(*n),
+            CallResult::Hex(n) => Some(*n as i64),
+            CallResult::Unknown => None,
+            CallResult::Error(_) => Some(-1),
+        }
+    }
+}
+
+impl Display for CallResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallResult::Int(n) => write!(f, "{}", n),
+            CallResult::Hex(n) => write!(f, "{:#x}", n),
+            CallResult::Unknown => write!(f, "?"),
+            CallResult::Error(errno) => match errno.name() {
+                Some(name) => write!(f, "-1 {}", name),
+                None => write!(f, "-1"),
+            },
+        }
+    }
+}
+
+/// Information that `strace` prints after the result of a call.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CallInfo<'a> {
+    /// The text in parentheses, e.g.: the description of an error, or `flags O_RDONLY` for `fcntl(fd, F_GETFL)`
+    pub comment: Option<&'a str>,
+    /// How long the call took, which is printed with `-T`
+    pub duration: Option<Duration>,
+}
+
+impl<'a> CallInfo<'a> {
+    /// Parses the information after a result, returning `None` if there wasn't any.
+    pub fn from_pest(pair: Pair<'a, Rule>) -> Option<CallInfo<'a>> {
+        let mut info = CallInfo {
+            comment: None,
+            duration: None,
+        };
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::call_comment => info.comment = Some(pair.as_str()),
+                // NOTE: this may be `<unavailable>` if the process was killed during the call
+                Rule::duration => {
+                    info.duration = pair
+                        .into_inner()
+                        .next()
+                        .map(|n| decode_timestamp(n.as_str()))
+                }
+                _ => unreachable!("{:?}", pair.as_rule()),
+            }
+        }
+
+        Some(info).filter(|info| info.comment.is_some() || info.duration.is_some())
+    }
+}
+
+impl<'a> Display for CallInfo<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let comment = self.comment.map(|comment| format!("({})", comment));
+        let duration = self
+            .duration
+            .map(|d| format!("<{}.{:06}>", d.as_secs(), d.subsec_micros()));
+        let parts = comment.into_iter().chain(duration).collect::<Vec<_>>();
+        write!(f, "{}", parts.join(" "))
+    }
+}
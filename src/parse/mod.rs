@@ -1,3 +1,5 @@
+pub mod call_result;
+pub mod stitch;
 pub mod strace_line;
 pub mod strace_token;
 pub mod string;
@@ -5,6 +7,7 @@ pub mod timestamp;
 
 use pest_derive::*;
 
+pub use call_result::{CallInfo, CallResult};
 pub use strace_line::StraceLine;
 pub use strace_token::StraceToken;
 
@@ -16,10 +19,13 @@ pub struct StraceParser;
 mod tests {
     use std::time::Duration;
 
+    use super::call_result::CallInfo;
+    use super::call_result::CallResult::*;
     use super::strace_line::StraceLine;
     use super::strace_token::StraceToken::*;
+    use crate::errno::Errno;
 
-    fn p(line: &str) -> StraceLine {
+    fn p(line: &str) -> StraceLine<'_> {
         StraceLine::from_str(line).unwrap()
     }
 
@@ -64,11 +70,22 @@ mod tests {
         assert_serde!(r#"/usr/bin/strace: Process 42 attached"#);
     }
 
+    #[test]
+    fn serialize_non_utf8() {
+        // bytes that aren't valid UTF-8 stay escaped, rather than the whole string
+        assert_eq!(
+            StraceLine::serialize(&p(
+                r#"openat(AT_FDCWD, "\x2f\x63\x61\x66\xe9\x2e\x74\x78\x74", O_RDONLY) = 3"#
+            )),
+            r#"openat(AT_FDCWD, "/caf\xe9.txt", O_RDONLY) = 3"#
+        );
+    }
+
     #[test]
     fn process_attached() {
         assert_eq!(
             p(r#"/usr/bin/strace: Process 1807404 attached"#).inner,
-            ProcessAttach(1807404)
+            ProcessAttach("/usr/bin/strace", 1807404)
         );
     }
 
@@ -80,6 +97,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn bare_pid() {
+        assert_eq!(
+            p(r#"4242  1611916273.692217 close(3) = 0"#),
+            StraceLine {
+                pid: Some(4242),
+                time: Some(Duration::from_micros(1611916273692217)),
+                inner: Call {
+                    name: "close",
+                    args: vec![Expr(vec![Number(3)])],
+                    result: Some(Int(0)),
+                    info: None,
+                }
+            }
+        );
+        assert_eq!(
+            p(r#"4242  +++ exited with 1 +++"#),
+            StraceLine {
+                pid: Some(4242),
+                time: None,
+                inner: Exit(1)
+            }
+        );
+    }
+
     #[test]
     fn process_exit() {
         assert_eq!(
@@ -143,7 +185,7 @@ mod tests {
                     String("/etc/ld.so.cache"),
                     Expr(vec![Ident("O_RDONLY"), Op("|"), Ident("O_CLOEXEC")])
                 ],
-                result: Some(3),
+                result: Some(Int(3)),
                 info: None,
             }
         );
@@ -157,8 +199,11 @@ mod tests {
             Call {
                 name: "access",
                 args: vec![String("/etc/ld.so.preload"), Expr(vec![Ident("R_OK")])],
-                result: Some(-1),
-                info: Some("ENOENT (No such file or directory)")
+                result: Some(Error(Errno(libc::ENOENT))),
+                info: Some(CallInfo {
+                    comment: Some("No such file or directory"),
+                    duration: None
+                })
             }
         );
     }
@@ -174,7 +219,7 @@ mod tests {
                     Array(vec![String("ls")]),
                     Expr(vec![Number(0x7ffea94d6768)])
                 ],
-                result: Some(0),
+                result: Some(Int(0)),
                 info: None
             }
         );
@@ -191,7 +236,7 @@ mod tests {
                     Array(vec![String("ls")]),
                     Expr(vec![Number(0x7ffea94d6768)])
                 ],
-                result: Some(0),
+                result: Some(Int(0)),
                 info: None
             }
         );
@@ -213,7 +258,7 @@ mod tests {
                         ("st_size", Expr(vec![Number(282443)]))
                     ])
                 ],
-                result: Some(0),
+                result: Some(Int(0)),
                 info: None
             }
         )
@@ -234,7 +279,7 @@ mod tests {
                     ),
                     Expr(vec![Number(256)])
                 ],
-                result: Some(36),
+                result: Some(Int(36)),
                 info: None
             }
         );
@@ -266,7 +311,7 @@ mod tests {
                         ),
                     ])
                 ],
-                result: Some(0),
+                result: Some(Int(0)),
                 info: None
             }
         );
@@ -282,7 +327,7 @@ mod tests {
                 inner: Call {
                     name: "access",
                     args: vec![String("/etc/ld.so.preload"), Expr(vec![Ident("R_OK")])],
-                    result: Some(-1),
+                    result: Some(Int(-1)),
                     info: None
                 }
             }
@@ -299,7 +344,7 @@ mod tests {
                 inner: Call {
                     name: "access",
                     args: vec![String("/etc/ld.so.preload"), Expr(vec![Ident("R_OK")])],
-                    result: Some(-1),
+                    result: Some(Int(-1)),
                     info: None
                 }
             }
@@ -316,7 +361,7 @@ mod tests {
                 inner: Call {
                     name: "access",
                     args: vec![String("/etc/ld.so.preload"), Expr(vec![Ident("R_OK")])],
-                    result: Some(-1),
+                    result: Some(Int(-1)),
                     info: None
                 }
             }
@@ -352,6 +397,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn signals_with_pid() {
+        assert_eq!(
+            p(r#"[pid 42] 1611916273.692217 --- SIGPIPE {si_signo=SIGPIPE, si_code=SI_USER} ---"#),
+            StraceLine {
+                pid: Some(42),
+                time: Some(Duration::from_micros(1611916273692217)),
+                inner: Signal(
+                    "SIGPIPE",
+                    vec![Hash(vec![
+                        ("si_signo", Expr(vec![Ident("SIGPIPE")])),
+                        ("si_code", Expr(vec![Ident("SI_USER")])),
+                    ])]
+                )
+            }
+        );
+    }
+
     #[test]
     fn signals_truncated() {
         assert_eq!(
@@ -383,7 +446,7 @@ mod tests {
                     BitSet(false, vec![Ident("CHLD"), Ident("TTOU")]),
                     Array(vec![])
                 ],
-                result: Some(0),
+                result: Some(Int(0)),
                 info: None
             }
         );
@@ -396,7 +459,7 @@ mod tests {
             Call {
                 name: "sigprocmask",
                 args: vec![Expr(vec![Ident("SIG_UNBLOCK")]), BitSet(true, vec![]), Null],
-                result: Some(0),
+                result: Some(Int(0)),
                 info: None
             }
         );
@@ -420,9 +483,237 @@ mod tests {
                         Expr(vec![Ident("echo")])
                     ])
                 ],
-                result: Some(0),
+                result: Some(Int(0)),
+                info: None
+            }
+        );
+    }
+
+    #[test]
+    fn call_args_named() {
+        assert_eq!(
+            p(r#"clone(child_stack=NULL, flags=CLONE_CHILD_SETTID|SIGCHLD, child_tidptr=0x7f52e8e0da10) = 4242"#)
+                .inner,
+            Call {
+                name: "clone",
+                args: vec![
+                    Named("child_stack", Box::new(Null)),
+                    Named(
+                        "flags",
+                        Box::new(Expr(vec![
+                            Ident("CLONE_CHILD_SETTID"),
+                            Op("|"),
+                            Ident("SIGCHLD")
+                        ]))
+                    ),
+                    Named("child_tidptr", Box::new(Expr(vec![Number(0x7f52e8e0da10)]))),
+                ],
+                result: Some(Int(4242)),
+                info: None
+            }
+        );
+    }
+
+    #[test]
+    fn call_args_changed() {
+        assert_eq!(
+            p(r#"clone3({flags=CLONE_VM|CLONE_VFORK, exit_signal=SIGCHLD} => {parent_tid=[4243]}, 88) = 4243"#)
+                .inner,
+            Call {
+                name: "clone3",
+                args: vec![
+                    Changed(
+                        Box::new(Hash(vec![
+                            (
+                                "flags",
+                                Expr(vec![Ident("CLONE_VM"), Op("|"), Ident("CLONE_VFORK")])
+                            ),
+                            ("exit_signal", Expr(vec![Ident("SIGCHLD")])),
+                        ])),
+                        Box::new(Hash(vec![(
+                            "parent_tid",
+                            Array(vec![Expr(vec![Number(4243)])])
+                        )]))
+                    ),
+                    Expr(vec![Number(88)]),
+                ],
+                result: Some(Int(4243)),
+                info: None
+            }
+        );
+    }
+
+    #[test]
+    fn call_args_fd() {
+        assert_eq!(
+            p(r#"fstat(3</usr/lib/libc.so.6>, {st_mode=S_IFREG|0755, st_size=1922136, ...}) = 0"#)
+                .inner,
+            Call {
+                name: "fstat",
+                args: vec![
+                    Fd(Box::new(Number(3)), "/usr/lib/libc.so.6"),
+                    Hash(vec![
+                        (
+                            "st_mode",
+                            Expr(vec![Ident("S_IFREG"), Op("|"), Number(0o755)])
+                        ),
+                        ("st_size", Expr(vec![Number(1922136)]))
+                    ])
+                ],
+                result: Some(Int(0)),
                 info: None
             }
         );
+        assert_eq!(
+            p(r#"openat(AT_FDCWD</home/user>, "file", O_RDONLY) = 3</home/user/file>"#).inner,
+            Call {
+                name: "openat",
+                args: vec![
+                    Fd(Box::new(Ident("AT_FDCWD")), "/home/user"),
+                    String("file"),
+                    Expr(vec![Ident("O_RDONLY")])
+                ],
+                result: Some(Int(3)),
+                info: None
+            }
+        );
+    }
+
+    #[test]
+    fn call_args_fd_special() {
+        assert_eq!(
+            p(r#"write(1</dev/pts/0<char 136:0>>, "hi", 2) = 2"#).inner,
+            Call {
+                name: "write",
+                args: vec![
+                    Fd(Box::new(Number(1)), "/dev/pts/0"),
+                    String("hi"),
+                    Expr(vec![Number(2)])
+                ],
+                result: Some(Int(2)),
+                info: None
+            }
+        );
+        assert_eq!(
+            p(r#"read(5<socket:[1234]>, "", 8) = 0"#).inner,
+            Call {
+                name: "read",
+                args: vec![
+                    Fd(Box::new(Number(5)), "socket:[1234]"),
+                    String(""),
+                    Expr(vec![Number(8)])
+                ],
+                result: Some(Int(0)),
+                info: None
+            }
+        );
+        assert_eq!(
+            p(r#"close(4<TCP:[127.0.0.1:8080->127.0.0.1:41234]>) = 0"#).inner,
+            Call {
+                name: "close",
+                args: vec![Fd(
+                    Box::new(Number(4)),
+                    "TCP:[127.0.0.1:8080->127.0.0.1:41234]"
+                )],
+                result: Some(Int(0)),
+                info: None
+            }
+        );
+    }
+
+    #[test]
+    fn call_results() {
+        let result = |line| match p(line).inner {
+            Call { result, .. } => result,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            result(
+                r#"mmap(NULL, 8192, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0) = 0x7f3a2c000000"#
+            ),
+            Some(Hex(0x7f3a2c000000))
+        );
+        assert_eq!(
+            result(r#"lseek(3, 0, SEEK_END) = 8589934592"#),
+            Some(Int(8589934592))
+        );
+        assert_eq!(result(r#"exit_group(0) = ?"#), Some(Unknown));
+        assert_eq!(
+            result(r#"ioctl(1, TCGETS, 0x7ffc) = -1 ENOTTY (Inappropriate ioctl for device)"#),
+            Some(Error(Errno(libc::ENOTTY)))
+        );
+        assert_eq!(
+            result(
+                r#"wait4(-1, 0x7ffc, 0, NULL) = ? ERESTARTSYS (To be restarted if SA_RESTART is set)"#
+            ),
+            Some(Unknown)
+        );
+        assert_eq!(result(r#"makedev(0xe2, 0)"#), None);
+    }
+
+    #[test]
+    fn call_info() {
+        let info = |line| match p(line).inner {
+            Call { info, .. } => info,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            info(r#"fcntl(3, F_GETFL) = 0x8000 (flags O_RDONLY|O_LARGEFILE)"#),
+            Some(CallInfo {
+                comment: Some("flags O_RDONLY|O_LARGEFILE"),
+                duration: None
+            })
+        );
+        assert_eq!(
+            info(r#"poll([{fd=3, events=POLLIN}], 1, 0) = 0 (Timeout) <0.000012>"#),
+            Some(CallInfo {
+                comment: Some("Timeout"),
+                duration: Some(Duration::from_micros(12))
+            })
+        );
+        assert_eq!(
+            info(
+                r#"select(4, [3], NULL, NULL, {tv_sec=1, tv_usec=0}) = 1 (in [3], left {tv_sec=0, tv_usec=999})"#
+            ),
+            Some(CallInfo {
+                comment: Some("in [3], left {tv_sec=0, tv_usec=999}"),
+                duration: None
+            })
+        );
+        assert_eq!(
+            info(r#"openat(AT_FDCWD, "/etc", O_RDONLY) = 3 <1.500000>"#),
+            Some(CallInfo {
+                comment: None,
+                duration: Some(Duration::from_millis(1500))
+            })
+        );
+        assert_eq!(info(r#"exit_group(0) = ? <unavailable>"#), None);
+        assert_eq!(info(r#"close(3) = 0"#), None);
+
+        assert_serde!(r#"stat("/", 0x7ffc) = -1 EACCES (Permission denied) <0.000012>"#);
+    }
+
+    #[test]
+    fn unfinished_and_resumed() {
+        assert_eq!(
+            p(r#"[pid 42] openat(AT_FDCWD, "/etc/passwd", O_RDONLY <unfinished ...>"#),
+            StraceLine {
+                pid: Some(42),
+                time: None,
+                inner: Unfinished("openat", r#"AT_FDCWD, "/etc/passwd", O_RDONLY "#)
+            }
+        );
+        assert_eq!(
+            p(r#"[pid 42] 1611916273.692217 <... read resumed>"abc", 4096) = 3"#),
+            StraceLine {
+                pid: Some(42),
+                time: Some(Duration::from_micros(1611916273692217)),
+                inner: Resumed("read", r#""abc", 4096) = 3"#)
+            }
+        );
+        assert_serde!(r#"read(3, <unfinished ...>"#);
+        assert_serde!(r#"<... read resumed>"abc", 4096) = 3"#);
     }
 }
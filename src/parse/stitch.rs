@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::parse::{StraceLine, StraceToken};
+
+/// What to do with a line after it has been given to a `Stitcher`.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Stitched {
+    /// The line is complete, and can be handled as is
+    Complete,
+    /// The line is the first half of a call, which is completed later by a "resumed" line
+    Unfinished,
+    /// The line completed an unfinished call, which should be parsed and handled in its place
+    Resumed {
+        pid: Option<i32>,
+        time: Option<Duration>,
+        call: String,
+    },
+    /// The line resumed a call that was never started (e.g.: when attaching to a running process)
+    Orphaned,
+}
+
+/// Joins calls that `strace` splits across two lines when another process makes a call at the same time, e.g.:
+///
+/// ```text
+/// [pid 42] openat(AT_FDCWD, "/etc/passwd", O_RDONLY <unfinished ...>
+/// [pid 43] close(3) = 0
+/// [pid 42] <... openat resumed>) = 3
+/// ```
+#[derive(Debug, Default)]
+pub struct Stitcher {
+    /// The start of each unfinished call, along with when it was made
+    unfinished: HashMap<Option<i32>, (Option<Duration>, String)>,
+}
+
+impl Stitcher {
+    pub fn stitch(&mut self, line: &StraceLine) -> Stitched {
+        match &line.inner {
+            StraceToken::Unfinished(name, args) => {
+                let call = format!("{}({}", name, args.trim_end());
+                self.unfinished.insert(line.pid, (line.time, call));
+                Stitched::Unfinished
+            }
+            StraceToken::Resumed(name, rest) => match self.unfinished.remove(&line.pid) {
+                Some((time, call)) if call.starts_with(&format!("{}(", name)) => {
+                    Stitched::Resumed {
+                        pid: line.pid,
+                        time,
+                        call: format!("{}{}", call, rest),
+                    }
+                }
+                _ => Stitched::Orphaned,
+            },
+            // a process can be killed in the middle of a call
+            StraceToken::Exit(_) | StraceToken::Kill(_) => {
+                self.unfinished.remove(&line.pid);
+                Stitched::Complete
+            }
+            _ => Stitched::Complete,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Stitched, Stitcher};
+    use crate::parse::StraceLine;
+
+    fn stitch(stitcher: &mut Stitcher, line: &str) -> Stitched {
+        stitcher.stitch(&StraceLine::from_str(line).unwrap())
+    }
+
+    #[test]
+    fn stitch_per_pid() {
+        let mut stitcher = Stitcher::default();
+        assert_eq!(
+            stitch(
+                &mut stitcher,
+                r#"[pid 1] 1.000001 read(3, <unfinished ...>"#
+            ),
+            Stitched::Unfinished
+        );
+        assert_eq!(
+            stitch(&mut stitcher, r#"[pid 2] close(3) = 0"#),
+            Stitched::Complete
+        );
+        assert_eq!(
+            stitch(&mut stitcher, r#"[pid 2] <... read resumed>"", 8) = 0"#),
+            Stitched::Orphaned
+        );
+        assert_eq!(
+            stitch(
+                &mut stitcher,
+                r#"[pid 1] 1.000009 <... read resumed>"", 8) = 0"#
+            ),
+            Stitched::Resumed {
+                pid: Some(1),
+                time: Some(Duration::from_micros(1_000_001)),
+                call: r#"read(3,"", 8) = 0"#.to_string()
+            }
+        );
+
+        // unfinished calls are discarded when their process exits
+        stitch(&mut stitcher, r#"[pid 1] stat("/", <unfinished ...>"#);
+        stitch(&mut stitcher, r#"[pid 1] +++ exited with 0 +++"#);
+        assert_eq!(
+            stitch(&mut stitcher, r#"[pid 1] <... stat resumed>0x7ffc) = 0"#),
+            Stitched::Orphaned
+        );
+    }
+}
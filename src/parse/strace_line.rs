@@ -19,11 +19,11 @@ impl<'a> StraceLine<'a> {
     pub fn serialize(&self) -> String {
         let pid = match self.pid {
             Some(pid) => format!("[pid {}] ", pid),
-            None => format!(""),
+            None => String::new(),
         };
         let time = match self.time {
             Some(time) => format!("{}.{} ", time.as_secs(), time.subsec_micros()),
-            None => format!(""),
+            None => String::new(),
         };
         let inner = self.inner.serialize();
         format!("{}{}{}", pid, time, inner)
@@ -33,7 +33,9 @@ impl<'a> StraceLine<'a> {
         self.inner.walk(f);
     }
 
-    pub fn from_str(line: &str) -> Result<StraceLine> {
+    // NOTE: `FromStr` can't be implemented since the parsed line borrows from the input
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(line: &str) -> Result<StraceLine<'_>> {
         // the "strace" rule contains a line
         let mut root_pairs = StraceParser::parse(Rule::strace, line)?
             .next()
@@ -46,33 +48,22 @@ impl<'a> StraceLine<'a> {
             1 => {
                 let pair = root_pairs.pop().unwrap();
                 match pair.as_rule() {
-                    Rule::signal => {
+                    Rule::permission_denied | Rule::process_attach => {
+                        let rule = pair.as_rule();
                         let mut inner = pair.into_inner();
-                        let name = inner.next().unwrap().as_str();
-                        let vars = match inner.next() {
-                            Some(pair) => vec![StraceToken::from_pest(pair)],
-                            None => vec![],
-                        };
+                        let program = inner.next().unwrap().as_str();
+                        let pid = inner.next().unwrap().as_str().parse().unwrap();
                         Ok(StraceLine {
                             pid: None,
                             time: None,
-                            inner: StraceToken::Signal(name, vars),
+                            inner: match rule {
+                                Rule::permission_denied => {
+                                    StraceToken::PermissionDenied(program, pid)
+                                }
+                                _ => StraceToken::ProcessAttach(program, pid),
+                            },
                         })
                     }
-                    Rule::permission_denied => Ok(StraceLine {
-                        pid: None,
-                        time: None,
-                        inner: StraceToken::PermissionDenied(
-                            pair.into_inner().as_str().parse().unwrap(),
-                        ),
-                    }),
-                    Rule::process_attach => Ok(StraceLine {
-                        pid: None,
-                        time: None,
-                        inner: StraceToken::ProcessAttach(
-                            pair.into_inner().as_str().parse().unwrap(),
-                        ),
-                    }),
                     Rule::process_detach => Ok(StraceLine {
                         pid: None,
                         time: None,
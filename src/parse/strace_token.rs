@@ -1,16 +1,18 @@
 use std::fmt::{self, Display};
 
 use pest::iterators::Pair;
-use which::which;
 
+use crate::parse::call_result::{CallInfo, CallResult};
 use crate::parse::string::decode_hex;
 use crate::parse::Rule;
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum StraceToken<'a> {
     Signal(&'a str, Vec<StraceToken<'a>>),
-    PermissionDenied(i32),
-    ProcessAttach(i32),
+    /// The program that printed the message (i.e.: the path `strace` was run as), and the pid it couldn't attach to
+    PermissionDenied(&'a str, i32),
+    /// The program that printed the message, and the pid that it attached to
+    ProcessAttach(&'a str, i32),
     ProcessDetach(i32),
     Kill(&'a str),
     Exit(i32),
@@ -18,10 +20,18 @@ pub enum StraceToken<'a> {
     Call {
         name: &'a str,
         args: Vec<StraceToken<'a>>,
-        result: Option<i32>,
-        info: Option<&'a str>,
+        result: Option<CallResult>,
+        info: Option<CallInfo<'a>>,
     },
+    /// The name and arguments of a call that was interrupted before it returned
+    Unfinished(&'a str, &'a str),
+    /// The name of an interrupted call, and the rest of its arguments and result
+    Resumed(&'a str, &'a str),
     Expr(Vec<StraceToken<'a>>),
+    Named(&'a str, Box<StraceToken<'a>>),
+    Changed(Box<StraceToken<'a>>, Box<StraceToken<'a>>),
+    /// A file descriptor (or `AT_FDCWD`) along with what it refers to, as decoded by `--decode-fds`
+    Fd(Box<StraceToken<'a>>, &'a str),
     Hash(Vec<(&'a str, StraceToken<'a>)>),
     Array(Vec<StraceToken<'a>>),
     List(Vec<StraceToken<'a>>),
@@ -39,8 +49,13 @@ impl<'a> StraceToken<'a> {
         match self {
             StraceToken::Expr(items) => {
                 let items = items.iter().map(Self::serialize).collect::<Vec<_>>();
-                format!("{}", items.join(""))
+                items.join("")
             }
+            StraceToken::Named(name, value) => format!("{}={}", name, Self::serialize(value)),
+            StraceToken::Changed(before, after) => {
+                format!("{} => {}", Self::serialize(before), Self::serialize(after))
+            }
+            StraceToken::Fd(fd, path) => format!("{}<{}>", Self::serialize(fd), decode_hex(path)),
             StraceToken::Array(items) => {
                 let items = items.iter().map(Self::serialize).collect::<Vec<_>>();
                 format!("[{}]", items.join(", "))
@@ -69,18 +84,20 @@ impl<'a> StraceToken<'a> {
                 let args = args.iter().map(Self::serialize).collect::<Vec<_>>();
                 let result = match result {
                     Some(n) => format!(" = {}", n),
-                    None => format!(""),
+                    None => String::new(),
                 };
                 match info {
                     Some(info) => format!("{}({}){} {}", name, args.join(", "), result, info),
                     None => format!("{}({}){}", name, args.join(", "), result),
                 }
             }
+            StraceToken::Unfinished(name, args) => {
+                format!("{}({} <unfinished ...>", name, args.trim_end())
+            }
+            StraceToken::Resumed(name, rest) => format!("<... {} resumed>{}", name, rest),
             StraceToken::String(s) => format!("\"{}\"", decode_hex(s)),
             StraceToken::Number(n) => format!("{:#x}", n),
-            StraceToken::Ident(inner) | StraceToken::Op(inner) => {
-                format!("{}", inner)
-            }
+            StraceToken::Ident(inner) | StraceToken::Op(inner) => inner.to_string(),
             StraceToken::Signal(name, vars) => format!(
                 "--- {}{} ---",
                 name,
@@ -89,27 +106,22 @@ impl<'a> StraceToken<'a> {
                     .collect::<Vec<_>>()
                     .join(", ")
             ),
-            StraceToken::PermissionDenied(pid) => {
+            StraceToken::PermissionDenied(program, pid) => {
                 format!(
                     "{}: attach: ptrace(PTRACE_SEIZE, {}): Operation not permitted",
-                    which("strace").unwrap().display(),
-                    pid
+                    program, pid
                 )
             }
-            StraceToken::ProcessAttach(pid) => {
-                format!(
-                    "{}: Process {} attached",
-                    which("strace").unwrap().display(),
-                    pid
-                )
+            StraceToken::ProcessAttach(program, pid) => {
+                format!("{}: Process {} attached", program, pid)
             }
             StraceToken::ProcessDetach(pid) => {
                 format!("strace: Process {} detached", pid)
             }
             StraceToken::Exit(code) => format!("+++ exited with {} +++", code),
             StraceToken::Kill(sig) => format!("+++ killed by {} +++", sig),
-            StraceToken::Truncated => format!("..."),
-            StraceToken::Null => format!("NULL"),
+            StraceToken::Truncated => "...".to_string(),
+            StraceToken::Null => "NULL".to_string(),
         }
     }
 
@@ -122,6 +134,11 @@ impl<'a> StraceToken<'a> {
             StraceToken::Call { args, .. } => args.iter().for_each(|arg| arg.walk(f)),
             StraceToken::Expr(items) => items.iter().for_each(|item| item.walk(f)),
             StraceToken::Hash(items) => items.iter().for_each(|(_, item)| item.walk(f)),
+            StraceToken::Named(_, value) | StraceToken::Fd(value, _) => value.walk(f),
+            StraceToken::Changed(before, after) => {
+                before.walk(f);
+                after.walk(f);
+            }
             StraceToken::Array(items) => items.iter().for_each(|item| item.walk(f)),
             _ => {}
         }
@@ -136,6 +153,11 @@ impl<'a> StraceToken<'a> {
             StraceToken::Call { args, .. } => args.iter().for_each(|arg| arg.walk_mut(f)),
             StraceToken::Expr(items) => items.iter().for_each(|item| item.walk_mut(f)),
             StraceToken::Hash(items) => items.iter().for_each(|(_, item)| item.walk_mut(f)),
+            StraceToken::Named(_, value) | StraceToken::Fd(value, _) => value.walk_mut(f),
+            StraceToken::Changed(before, after) => {
+                before.walk_mut(f);
+                after.walk_mut(f);
+            }
             StraceToken::Array(items) => items.iter().for_each(|item| item.walk_mut(f)),
             _ => {}
         }
@@ -154,6 +176,32 @@ impl<'a> StraceToken<'a> {
         strs
     }
 
+    pub fn idents(&self) -> Vec<&'a str> {
+        let mut idents = vec![];
+        self.walk_mut(&mut |token| {
+            if let StraceToken::Ident(s) = token {
+                idents.push(*s);
+            }
+
+            true
+        });
+
+        idents
+    }
+
+    /// Returns the value of a token that's a plain number, such as a file descriptor.
+    pub fn number(&self) -> Option<i64> {
+        match self {
+            StraceToken::Number(n) => Some(*n),
+            StraceToken::Fd(fd, _) => fd.number(),
+            StraceToken::Expr(items) => match items.as_slice() {
+                [StraceToken::Number(n)] => Some(*n),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     pub fn from_pest(pair: Pair<Rule>) -> StraceToken {
         match pair.as_rule() {
             Rule::line => unreachable!(),
@@ -180,22 +228,20 @@ impl<'a> StraceToken<'a> {
             Rule::call => {
                 let mut inner_pairs = pair.into_inner().collect::<Vec<_>>();
                 let name = inner_pairs.remove(0).as_str();
-                let (result, info): (Option<i32>, Option<&str>) =
-                    match inner_pairs[inner_pairs.len() - 1].as_rule() {
-                        Rule::call_result => {
-                            let mut inner = inner_pairs.pop().unwrap().into_inner();
-                            let result = inner
-                                .next()
-                                .unwrap()
-                                .as_str()
-                                .trim()
-                                .parse::<i32>()
-                                .unwrap();
+                let (result, info) = match inner_pairs[inner_pairs.len() - 1].as_rule() {
+                    Rule::call_result => {
+                        let mut inner = inner_pairs.pop().unwrap().into_inner().collect::<Vec<_>>();
+                        let info = CallInfo::from_pest(inner.pop().unwrap());
+                        let errno = match inner.last().map(|p| p.as_rule()) {
+                            Some(Rule::errno) => inner.pop().map(|p| p.as_str()),
+                            _ => None,
+                        };
+                        let result = CallResult::from_pest(inner.pop().unwrap(), errno);
 
-                            (Some(result), inner.next().map(|p| p.as_str()))
-                        }
-                        _ => (None, None),
-                    };
+                        (Some(result), info)
+                    }
+                    _ => (None, None),
+                };
 
                 let args = inner_pairs.into_iter().map(Self::from_pest).collect();
                 StraceToken::Call {
@@ -205,18 +251,53 @@ impl<'a> StraceToken<'a> {
                     info,
                 }
             }
+            Rule::fd => {
+                let mut inner = pair.into_inner();
+                let fd = Self::from_pest(inner.next().unwrap());
+                StraceToken::Fd(Box::new(fd), inner.next().unwrap().as_str())
+            }
             Rule::expr => StraceToken::Expr(pair.into_inner().map(Self::from_pest).collect()),
+            Rule::named => {
+                let mut inner = pair.into_inner();
+                let name = inner.next().unwrap().as_str();
+                StraceToken::Named(name, Box::new(Self::from_pest(inner.next().unwrap())))
+            }
+            Rule::changed => {
+                let mut inner = pair.into_inner().map(Self::from_pest);
+                let before = inner.next().unwrap();
+                StraceToken::Changed(Box::new(before), Box::new(inner.next().unwrap()))
+            }
+            Rule::unfinished | Rule::resumed => {
+                let rule = pair.as_rule();
+                let mut inner = pair.into_inner();
+                let name = inner.next().unwrap().as_str();
+                let rest = inner.next().unwrap().as_str();
+                match rule {
+                    Rule::unfinished => StraceToken::Unfinished(name, rest),
+                    _ => StraceToken::Resumed(name, rest),
+                }
+            }
             Rule::exit => StraceToken::Exit(pair.into_inner().as_str().parse().unwrap()),
             Rule::kill => StraceToken::Kill(pair.into_inner().as_str()),
+            Rule::signal => {
+                let mut inner = pair.into_inner();
+                let name = inner.next().unwrap().as_str();
+                let vars = match inner.next() {
+                    Some(pair) => vec![StraceToken::from_pest(pair)],
+                    None => vec![],
+                };
+                StraceToken::Signal(name, vars)
+            }
             Rule::string => StraceToken::String(pair.into_inner().next().unwrap().as_str()),
             Rule::constant | Rule::ident => StraceToken::Ident(pair.as_str()),
             Rule::op => StraceToken::Op(pair.as_str()),
             Rule::number => {
                 let s = pair.as_str();
-                StraceToken::Number(if s.starts_with("0x") {
-                    i64::from_str_radix(&s[2..], 16).unwrap()
-                } else if s.len() > 1 && s.starts_with("0") {
-                    i64::from_str_radix(&s[1..], 8).unwrap()
+                // NOTE: hexadecimal and octal values are unsigned, and may not fit in a signed integer
+                StraceToken::Number(if let Some(hex) = s.strip_prefix("0x") {
+                    u64::from_str_radix(hex, 16).unwrap() as i64
+                } else if s.len() > 1 && s.starts_with('0') {
+                    u64::from_str_radix(&s[1..], 8).unwrap() as i64
                 } else {
                     s.parse().unwrap()
                 })
@@ -230,13 +311,22 @@ impl<'a> StraceToken<'a> {
             | Rule::number_hex
             | Rule::number_oct
             | Rule::value
+            | Rule::arg
             | Rule::trace
             | Rule::strace => unreachable!("{:?}", pair.as_rule()),
 
             // Rules consumed by other rules
             Rule::key_value
             | Rule::call_info
+            | Rule::call_comment
+            | Rule::unfinished_args
+            | Rule::resumed_rest
+            | Rule::errno
+            | Rule::duration
             | Rule::call_result
+            | Rule::unknown
+            | Rule::fd_path
+            | Rule::fd_info
             | Rule::comment_inner
             | Rule::string_inner
             | Rule::char => unreachable!("{:?}", dbg!(pair).as_rule()),
@@ -244,9 +334,10 @@ impl<'a> StraceToken<'a> {
             // Root-level rules handled in `StraceLine::from_str`
             Rule::timestamp
             | Rule::pid
-            | Rule::signal
+            | Rule::bare_pid
             | Rule::permission_denied
             | Rule::process_attach
+            | Rule::program
             | Rule::process_detach => {
                 unreachable!("{:?}", pair.as_rule())
             }
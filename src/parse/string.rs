@@ -1,11 +1,35 @@
-/// Attempts to decode a hexadecimally escaped string.
-/// Returns an owned version of the original string if decoding failed.
+/// Attempts to decode a hexadecimally escaped string, escaping any bytes that aren't valid UTF-8 as `\xNN` so that no
+/// information is lost. Returns an owned version of the original string if decoding failed.
 pub fn decode_hex(s: &str) -> String {
-    match hex::decode(s.replace(r"\x", "")) {
-        Ok(bytes) => match String::from_utf8(bytes) {
-            Ok(decoded_string) => decoded_string,
-            Err(_) => s.to_string(),
-        },
-        Err(_) => s.to_string(),
+    let bytes = match hex::decode(s.replace(r"\x", "")) {
+        Ok(bytes) => bytes,
+        Err(_) => return s.to_string(),
+    };
+
+    let mut decoded = String::new();
+    let mut rest = &bytes[..];
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                decoded.push_str(valid);
+                return decoded;
+            }
+            Err(e) => {
+                let (valid, invalid) = rest.split_at(e.valid_up_to());
+                // NOTE: the prefix was just checked to be valid
+                decoded.push_str(std::str::from_utf8(valid).unwrap());
+                let len = e.error_len().unwrap_or(invalid.len());
+                for b in &invalid[..len] {
+                    decoded.push_str(&format!("\\x{:02x}", b));
+                }
+                rest = &invalid[len..];
+            }
+        }
     }
 }
+
+/// Decodes a hexadecimally escaped string into its raw bytes.
+/// Returns the bytes of the original string if decoding failed.
+pub fn decode_hex_bytes(s: &str) -> Vec<u8> {
+    hex::decode(s.replace(r"\x", "")).unwrap_or_else(|_| s.as_bytes().to_vec())
+}
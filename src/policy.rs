@@ -0,0 +1,397 @@
+//! The paths that a program accessed during a recorded run, which can be enforced with Landlock on later runs.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::landlock::{self, Ruleset};
+use crate::FileAccess;
+
+/// The access rights needed to create, remove and rename the entries of a directory.
+const MODIFY_DIR: u64 = landlock::MAKE_REG
+    | landlock::MAKE_DIR
+    | landlock::MAKE_SYM
+    | landlock::REMOVE_FILE
+    | landlock::REMOVE_DIR
+    | landlock::REFER;
+
+/// The paths that were read, written and executed, as they're saved by `ftrace record`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Policy {
+    #[serde(default)]
+    pub read: BTreeSet<PathBuf>,
+    #[serde(default)]
+    pub write: BTreeSet<PathBuf>,
+    #[serde(default)]
+    pub execute: BTreeSet<PathBuf>,
+}
+
+impl Policy {
+    /// Reads a policy from a TOML file.
+    pub fn load(path: &Path) -> Result<Policy> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Writes the policy as TOML, starting with the given comment.
+    pub fn save(&self, path: &Path, comment: &str) -> Result<()> {
+        let contents = toml::to_string_pretty(self).context("Failed to serialize policy")?;
+        let comment = comment
+            .lines()
+            .map(|line| format!("# {}\n", line))
+            .collect::<String>();
+        fs::write(path, comment + &contents)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Adds a path that was accessed, ignoring any access that failed. Paths beneath the `/proc` directory of the
+    /// process that accessed them are saved beneath `/proc/self`, since its pid differs on each run.
+    pub fn add(&mut self, access: &FileAccess) {
+        if access.success != Some(true) || !is_valid(&access.path) {
+            return;
+        }
+
+        let path = normalize_proc(&access.path, access.pid);
+        if access.mode.is_read() {
+            self.read.insert(path.to_path_buf());
+        }
+        if access.mode.is_write() {
+            self.write.insert(path.to_path_buf());
+        }
+    }
+
+    /// Adds a program that was executed, along with the interpreter that the kernel runs it with (if any).
+    pub fn exec(&mut self, path: &Path) {
+        if !is_valid(path) || !self.execute.insert(path.to_path_buf()) {
+            return;
+        }
+
+        if let Some(interpreter) = interpreter(path) {
+            self.exec(&interpreter);
+        }
+    }
+
+    /// Builds a Landlock ruleset that allows the accesses in the policy, and denies everything else.
+    ///
+    /// Paths that no longer exist (e.g.: temporary files) are allowed beneath their nearest existing ancestor, and
+    /// written paths allow files to be created, removed and renamed in their directory. Paths beneath `/proc/self` are
+    /// allowed for the sandboxed program rather than for this process, and paths beneath the `/proc` directory of any
+    /// other process are skipped, since its pid won't be the same.
+    pub fn ruleset(&self) -> Result<Ruleset> {
+        let mut ruleset = Ruleset::new().context("Landlock isn't supported by this kernel")?;
+        for (path, access) in self.rules() {
+            let result = if path.starts_with("/proc/self") || path.starts_with("/proc/thread-self")
+            {
+                ruleset.allow_own(&path, access)
+            } else {
+                ruleset.allow(&path, access)
+            };
+            result.with_context(|| format!("Failed to allow access to {}", path.display()))?;
+        }
+
+        Ok(ruleset)
+    }
+
+    /// Returns the access rights to allow beneath each path of the ruleset.
+    fn rules(&self) -> BTreeMap<PathBuf, u64> {
+        let mut rules = BTreeMap::<PathBuf, u64>::new();
+        let mut allow = |path: &Path, access: u64| {
+            if is_other_process(path) {
+                log::warn!("skipping path of another process: {}", path.display());
+                return;
+            }
+            let path = &resolve_proc(path);
+            let (path, exists) = match path
+                .ancestors()
+                .find(|path| path.symlink_metadata().is_ok())
+            {
+                Some(ancestor) => (ancestor, ancestor == path),
+                None => return,
+            };
+            let access = match fs::metadata(path).map(|meta| meta.file_type()) {
+                // the rights of a directory apply to everything beneath it, so only allow it to be listed rather than
+                // allowing the files in it to be read or written
+                Ok(file_type) if file_type.is_dir() && exists => access & !landlock::FILE_ACCESS,
+                Ok(file_type) if file_type.is_char_device() || file_type.is_block_device() => {
+                    access | landlock::IOCTL_DEV
+                }
+                _ => access,
+            };
+            *rules.entry(path.to_path_buf()).or_default() |= access;
+        };
+
+        for path in &self.read {
+            allow(path, landlock::READ_FILE | landlock::READ_DIR);
+        }
+        for path in &self.write {
+            allow(path, landlock::WRITE_FILE | landlock::TRUNCATE);
+            let is_special = fs::metadata(path)
+                .map(|meta| !meta.is_file() && !meta.is_dir())
+                .unwrap_or(false);
+            match path.parent() {
+                // e.g.: writing to `/dev/null` doesn't allow `/dev` to be modified
+                Some(parent) if !is_special => allow(parent, MODIFY_DIR),
+                _ => {}
+            }
+        }
+        for path in &self.execute {
+            allow(path, landlock::EXECUTE | landlock::READ_FILE);
+        }
+
+        rules
+    }
+}
+
+/// Whether a path can be saved in a policy, which requires it to be valid UTF-8.
+fn is_valid(path: &Path) -> bool {
+    if path.to_str().is_none() {
+        log::warn!("skipping path that isn't valid UTF-8: {:?}", path);
+        return false;
+    }
+
+    true
+}
+
+/// Replaces the pid in a path beneath `/proc` with `self` if it's the pid of the process that accessed it, or with
+/// `thread-self` if it's the path of that thread, e.g.: `/proc/42/task/42/stat` becomes `/proc/thread-self/stat`.
+fn normalize_proc(path: &Path, pid: Option<i32>) -> Cow<'_, Path> {
+    let pid = match pid {
+        Some(pid) => pid.to_string(),
+        None => return Cow::Borrowed(path),
+    };
+    let rest = match path.strip_prefix(Path::new("/proc").join(&pid)) {
+        Ok(rest) => rest,
+        Err(_) => return Cow::Borrowed(path),
+    };
+
+    let (dir, rest) = match rest.strip_prefix(Path::new("task").join(&pid)) {
+        Ok(rest) => ("/proc/thread-self", rest),
+        Err(_) => ("/proc/self", rest),
+    };
+    if rest.as_os_str().is_empty() {
+        Cow::Owned(PathBuf::from(dir))
+    } else {
+        Cow::Owned(Path::new(dir).join(rest))
+    }
+}
+
+/// Resolves the symlinks in `/proc` that point into `/proc/self` (such as `/proc/mounts`), since they'd otherwise be
+/// resolved by this process rather than the sandboxed program.
+fn resolve_proc(path: &Path) -> PathBuf {
+    let mut components = match path.strip_prefix("/proc") {
+        Ok(rest) => rest.components(),
+        Err(_) => return path.to_path_buf(),
+    };
+    let link = match components.next() {
+        Some(name) => Path::new("/proc").join(name),
+        None => return path.to_path_buf(),
+    };
+    match fs::read_link(&link) {
+        Ok(target) if target.starts_with("self") || target.starts_with("thread-self") => {
+            let target = Path::new("/proc").join(target);
+            match components.as_path() {
+                // joining an empty path would add a trailing `/`
+                rest if rest.as_os_str().is_empty() => target,
+                rest => target.join(rest),
+            }
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Whether a path is beneath the `/proc` directory of a process given by its pid (rather than `/proc/self`).
+fn is_other_process(path: &Path) -> bool {
+    let mut components = match path.strip_prefix("/proc") {
+        Ok(rest) => rest.components(),
+        Err(_) => return false,
+    };
+    match components.next() {
+        Some(pid) => pid.as_os_str().as_bytes().iter().all(u8::is_ascii_digit),
+        None => false,
+    }
+}
+
+/// Returns the interpreter of a program, from either its `#!` line or the `PT_INTERP` header of an ELF binary.
+pub fn interpreter(path: &Path) -> Option<PathBuf> {
+    let mut header = vec![];
+    File::open(path)
+        .and_then(|file| file.take(256).read_to_end(&mut header))
+        .ok()?;
+
+    if let Some(line) = header.strip_prefix(b"#!") {
+        let line = line.split(|b| *b == b'\n').next()?;
+        let line = String::from_utf8_lossy(line);
+        return line.split_whitespace().next().map(PathBuf::from);
+    }
+
+    elf_interpreter(path).ok().flatten()
+}
+
+/// Returns the `PT_INTERP` header of an ELF binary, which is the dynamic loader of dynamically linked programs.
+fn elf_interpreter(path: &Path) -> io::Result<Option<PathBuf>> {
+    const PT_INTERP: u32 = 3;
+
+    let bytes = fs::read(path)?;
+    if !bytes.starts_with(b"\x7fELF") || bytes.len() < 64 {
+        return Ok(None);
+    }
+
+    let (is_64, is_le) = (bytes[4] == 2, bytes[5] == 1);
+    let read = |offset: usize, len: usize| -> Option<u64> {
+        let field = bytes.get(offset..offset + len)?;
+        let mut value = 0;
+        for i in 0..len {
+            let b = if is_le { field[len - 1 - i] } else { field[i] };
+            value = (value << 8) | b as u64;
+        }
+        Some(value)
+    };
+    // the offsets of the fields differ between 32 and 64-bit binaries, see `elf(5)`
+    let interpreter = || -> Option<PathBuf> {
+        let (phoff, phentsize, phnum) = if is_64 {
+            (read(0x20, 8)?, read(0x36, 2)?, read(0x38, 2)?)
+        } else {
+            (read(0x1c, 4)?, read(0x2a, 2)?, read(0x2c, 2)?)
+        };
+        let (phoff, phentsize, phnum) = (phoff as usize, phentsize as usize, phnum as usize);
+        for i in 0..phnum {
+            let header = phoff + i * phentsize;
+            if read(header, 4)? as u32 != PT_INTERP {
+                continue;
+            }
+
+            let offset = if is_64 {
+                read(header + 0x08, 8)?
+            } else {
+                read(header + 0x04, 4)?
+            } as usize;
+            let size = if is_64 {
+                read(header + 0x20, 8)?
+            } else {
+                read(header + 0x10, 4)?
+            } as usize;
+            let interpreter = bytes.get(offset..offset + size)?;
+            let interpreter = interpreter.split(|b| *b == 0).next()?;
+            return Some(PathBuf::from(String::from_utf8_lossy(interpreter).as_ref()));
+        }
+
+        None
+    };
+
+    Ok(interpreter())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::{is_other_process, normalize_proc, resolve_proc, Policy, MODIFY_DIR};
+    use crate::landlock::{IOCTL_DEV, READ_DIR, READ_FILE, TRUNCATE, WRITE_FILE};
+
+    #[test]
+    fn rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/main.c"), "").unwrap();
+
+        let policy = Policy {
+            read: vec![root.join("src"), root.join("src/main.c")]
+                .into_iter()
+                .collect(),
+            write: vec![root.join("build/tmp/main.o"), PathBuf::from("/dev/null")]
+                .into_iter()
+                .collect(),
+            execute: BTreeSet::new(),
+        };
+        let rules = policy.rules();
+
+        assert_eq!(rules[&root.join("src")], READ_DIR);
+        assert_eq!(rules[&root.join("src/main.c")], READ_FILE | READ_DIR);
+        // the missing file and its missing directory are both allowed beneath the nearest existing ancestor
+        assert_eq!(rules[root], WRITE_FILE | TRUNCATE | MODIFY_DIR);
+        assert_eq!(
+            rules[Path::new("/dev/null")],
+            WRITE_FILE | TRUNCATE | IOCTL_DEV
+        );
+        assert!(!rules.contains_key(Path::new("/dev")));
+        assert_eq!(rules.len(), 4);
+    }
+
+    #[test]
+    fn rules_proc() {
+        let policy = Policy {
+            read: vec![
+                PathBuf::from("/proc/1/status"),
+                PathBuf::from("/proc/mounts"),
+            ]
+            .into_iter()
+            .collect(),
+            ..Policy::default()
+        };
+
+        assert_eq!(
+            policy.rules().into_keys().collect::<Vec<_>>(),
+            vec![PathBuf::from("/proc/self/mounts")]
+        );
+    }
+
+    #[test]
+    fn proc_paths() {
+        assert_eq!(
+            normalize_proc(Path::new("/proc/42/status"), Some(42)),
+            Path::new("/proc/self/status")
+        );
+        assert_eq!(
+            normalize_proc(Path::new("/proc/42"), Some(42)),
+            Path::new("/proc/self")
+        );
+        assert_eq!(
+            normalize_proc(Path::new("/proc/42/task/42/comm"), Some(42)),
+            Path::new("/proc/thread-self/comm")
+        );
+        assert_eq!(
+            normalize_proc(Path::new("/proc/42/task/43/comm"), Some(42)),
+            Path::new("/proc/self/task/43/comm")
+        );
+        assert_eq!(
+            normalize_proc(Path::new("/proc/420/status"), Some(42)),
+            Path::new("/proc/420/status")
+        );
+        assert_eq!(
+            normalize_proc(Path::new("/proc/42/status"), None),
+            Path::new("/proc/42/status")
+        );
+
+        assert!(is_other_process(Path::new("/proc/1/status")));
+        assert!(is_other_process(Path::new("/proc/1")));
+        assert!(!is_other_process(Path::new("/proc/self/status")));
+        assert!(!is_other_process(Path::new("/proc/mounts")));
+        assert!(!is_other_process(Path::new("/proc")));
+        assert!(!is_other_process(Path::new("/tmp/1")));
+
+        assert_eq!(
+            resolve_proc(Path::new("/proc/mounts")),
+            Path::new("/proc/self/mounts")
+        );
+        assert_eq!(
+            resolve_proc(Path::new("/proc/net/tcp")),
+            Path::new("/proc/self/net/tcp")
+        );
+        assert_eq!(
+            resolve_proc(Path::new("/proc/cpuinfo")),
+            Path::new("/proc/cpuinfo")
+        );
+        assert_eq!(resolve_proc(Path::new("/etc/mtab")), Path::new("/etc/mtab"));
+    }
+}
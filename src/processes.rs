@@ -0,0 +1,518 @@
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::analysis::{FnInfo, FN_MAP};
+use crate::fs::normalize;
+use crate::parse::{string::decode_hex_bytes, CallResult, StraceLine, StraceToken};
+
+/// An open file descriptor of a process.
+#[derive(Debug, Clone)]
+pub struct Fd {
+    /// The path that was opened
+    pub path: PathBuf,
+    /// Whether the file descriptor is closed when the process calls `execve`
+    pub cloexec: bool,
+}
+
+pub type FdTable = HashMap<i32, Fd>;
+
+/// The state of a single traced process (or thread).
+#[derive(Debug, Clone)]
+pub struct Process {
+    /// The current working directory of the process, which is shared with threads created with `CLONE_FS`
+    pub cwd: Rc<RefCell<PathBuf>>,
+    /// The open file descriptors of the process, which are shared with threads created with `CLONE_FILES`
+    pub fds: Rc<RefCell<FdTable>>,
+    /// The program the process is running, if it's known
+    pub exe: Option<PathBuf>,
+}
+
+impl Process {
+    pub fn new(cwd: PathBuf) -> Process {
+        Process {
+            cwd: Rc::new(RefCell::new(cwd)),
+            fds: Rc::default(),
+            exe: None,
+        }
+    }
+
+    /// Reads the state of a running process from `/proc`.
+    pub fn from_proc(pid: i32) -> Option<Process> {
+        let cwd = proc_cwd(pid)?;
+        let mut fds = FdTable::new();
+        for entry in fs::read_dir(format!("/proc/{}/fd", pid)).ok()?.flatten() {
+            let fd = match entry.file_name().to_str().and_then(|n| n.parse().ok()) {
+                Some(fd) => fd,
+                None => continue,
+            };
+            let path = match fs::read_link(entry.path()) {
+                Ok(path) => path,
+                Err(_) => continue,
+            };
+
+            // the flags are printed in octal, e.g.: "flags:\t02100000"
+            let cloexec = fs::read_to_string(format!("/proc/{}/fdinfo/{}", pid, fd))
+                .ok()
+                .and_then(|info| {
+                    info.lines()
+                        .find_map(|line| line.strip_prefix("flags:"))
+                        .and_then(|flags| i32::from_str_radix(flags.trim(), 8).ok())
+                })
+                .map(|flags| flags & libc::O_CLOEXEC != 0)
+                .unwrap_or(false);
+
+            fds.insert(fd, Fd { path, cloexec });
+        }
+
+        Some(Process {
+            cwd: Rc::new(RefCell::new(cwd)),
+            fds: Rc::new(RefCell::new(fds)),
+            exe: fs::read_link(format!("/proc/{}/exe", pid)).ok(),
+        })
+    }
+
+    /// Returns the state of a child process created by this one.
+    fn fork(&self, share_fs: bool, share_files: bool) -> Process {
+        Process {
+            cwd: if share_fs {
+                self.cwd.clone()
+            } else {
+                Rc::new(RefCell::new(self.cwd.borrow().clone()))
+            },
+            fds: if share_files {
+                self.fds.clone()
+            } else {
+                Rc::new(RefCell::new(self.fds.borrow().clone()))
+            },
+            exe: self.exe.clone(),
+        }
+    }
+}
+
+/// Decodes a path from a hexadecimally escaped string.
+fn decode_path(s: &str) -> PathBuf {
+    PathBuf::from(OsString::from_vec(decode_hex_bytes(s)))
+}
+
+/// Returns the working directory of a running process.
+fn proc_cwd(pid: i32) -> Option<PathBuf> {
+    fs::read_link(format!("/proc/{}/cwd", pid)).ok()
+}
+
+/// Tracks the state of each traced process, so paths can be resolved in the context of the process that used them.
+///
+/// `strace` doesn't prefix its output with a pid until the traced program has forked, so the `None` entry refers to the
+/// initial process. Processes that are seen before the call that created them has returned (or whose parent wasn't
+/// traced) are assumed to have inherited the state of the initial process.
+#[derive(Debug)]
+pub struct Processes {
+    /// Whether the processes are being traced right now, and can therefore be inspected via `/proc`
+    live: bool,
+    processes: HashMap<Option<i32>, Process>,
+}
+
+impl Processes {
+    pub fn new(initial: Process, live: bool) -> Processes {
+        let mut processes = HashMap::new();
+        processes.insert(None, initial);
+
+        Processes { live, processes }
+    }
+
+    pub fn get(&mut self, pid: Option<i32>) -> &mut Process {
+        if !self.processes.contains_key(&pid) {
+            let process = self.processes[&None].fork(false, false);
+            self.processes.insert(pid, process);
+        }
+
+        self.processes.get_mut(&pid).unwrap()
+    }
+
+    /// Returns the path of an open file descriptor of the given process.
+    pub fn fd_path(&mut self, pid: Option<i32>, fd: i64) -> Option<PathBuf> {
+        let fds = self.get(pid).fds.borrow();
+        fds.get(&(fd as i32)).map(|fd| fd.path.clone())
+    }
+
+    /// Returns the path of a file descriptor argument, preferring the path that `strace` decoded (with
+    /// `--decode-fds`) over the tracked file descriptors. Anything that isn't a path (such as sockets) is ignored.
+    pub fn fd_arg_path(&mut self, pid: Option<i32>, arg: &StraceToken) -> Option<PathBuf> {
+        match arg {
+            StraceToken::Fd(_, path) => Some(decode_path(path)).filter(|path| path.is_absolute()),
+            arg => arg.number().and_then(|fd| self.fd_path(pid, fd)),
+        }
+    }
+
+    /// Resolves a path used by the given process into an absolute, normalised path.
+    pub fn resolve(&mut self, pid: Option<i32>, path: impl AsRef<Path>) -> PathBuf {
+        let cwd = self.get(pid).cwd.borrow().join(path);
+        normalize(&cwd)
+    }
+
+    /// Resolves a path found in the argument at `index` of a call made by the given process.
+    pub fn resolve_arg(
+        &mut self,
+        pid: Option<i32>,
+        fn_info: &FnInfo,
+        args: &[StraceToken],
+        index: usize,
+        path: impl AsRef<Path>,
+    ) -> PathBuf {
+        if let Some((target, link)) = fn_info.link_args {
+            if target == index {
+                if let Some(StraceToken::String(link_path)) = args.get(link) {
+                    let link_path =
+                        self.resolve_arg(pid, fn_info, args, link, decode_path(link_path));
+                    if let Some(dir) = link_path.parent() {
+                        return normalize(&dir.join(path));
+                    }
+                }
+            }
+        }
+
+        // NOTE: unless it was decoded, `AT_FDCWD` isn't a number so these paths are resolved against the working directory
+        let dirfd = fn_info.dirfd_arg(index).and_then(|i| args.get(i));
+        if let Some(dir) = dirfd.and_then(|fd| self.fd_arg_path(pid, fd)) {
+            return normalize(&dir.join(path));
+        }
+
+        self.resolve(pid, path)
+    }
+
+    /// Copies the file descriptor `old` to `new` in the given process.
+    fn dup(&mut self, pid: Option<i32>, old: Option<i64>, new: i32, cloexec: bool) {
+        let mut fds = self.get(pid).fds.borrow_mut();
+        if let Some(fd) = old.and_then(|old| fds.get(&(old as i32))).cloned() {
+            fds.insert(new, Fd { cloexec, ..fd });
+        } else {
+            fds.remove(&new);
+        }
+    }
+
+    /// Updates the state of the processes after a line has been handled.
+    pub fn update(&mut self, line: &StraceLine) {
+        let pid = line.pid;
+        match &line.inner {
+            StraceToken::ProcessAttach(_, child) if self.live => {
+                if let Some(process) = Process::from_proc(*child) {
+                    self.processes.insert(Some(*child), process);
+                }
+            }
+            StraceToken::Exit(_) | StraceToken::Kill(_) if pid.is_some() => {
+                self.processes.remove(&pid);
+            }
+            StraceToken::Call {
+                name,
+                args,
+                result: Some(CallResult::Int(result)),
+                ..
+            } if *result >= 0 => {
+                let result = *result as i32;
+                let has_flag = |i: usize, flag: &str| {
+                    args.get(i)
+                        .map(|arg| arg.idents().contains(&flag))
+                        .unwrap_or(false)
+                };
+                let arg = |i: usize| args.get(i).and_then(StraceToken::number);
+
+                match *name {
+                    "chdir" => {
+                        if let Some(StraceToken::String(path)) = args.first() {
+                            let cwd = self.resolve(pid, decode_path(path));
+                            *self.get(pid).cwd.borrow_mut() = cwd;
+                        }
+                    }
+                    "fchdir" => {
+                        let cwd = match args.first().and_then(|fd| self.fd_arg_path(pid, fd)) {
+                            Some(cwd) => Some(cwd),
+                            // NOTE: fall back to inspecting the process itself
+                            None => pid.filter(|_| self.live).and_then(proc_cwd),
+                        };
+                        if let Some(cwd) = cwd {
+                            *self.get(pid).cwd.borrow_mut() = cwd;
+                        }
+                    }
+                    "getcwd" => {
+                        if let Some(StraceToken::String(path)) = args.first() {
+                            *self.get(pid).cwd.borrow_mut() = decode_path(path);
+                        }
+                    }
+                    "clone" | "clone3" | "fork" | "vfork" if result > 0 => {
+                        let has_clone_flag =
+                            |flag| args.iter().any(|arg| arg.idents().contains(&flag));
+                        // threads always share their working directory with the rest of the process
+                        let share_fs = has_clone_flag("CLONE_FS") || has_clone_flag("CLONE_THREAD");
+                        let share_files = has_clone_flag("CLONE_FILES");
+                        let process = self.get(pid).fork(share_fs, share_files);
+                        match self.processes.entry(Some(result)) {
+                            // the child was seen before this call returned, but its state is shared with its parent
+                            Entry::Occupied(mut entry) => {
+                                let child = entry.get_mut();
+                                if share_fs {
+                                    // any `chdir` the child made has already happened
+                                    *process.cwd.borrow_mut() = child.cwd.borrow().clone();
+                                    child.cwd = process.cwd;
+                                }
+                                if share_files {
+                                    process.fds.borrow_mut().extend(
+                                        child.fds.borrow().iter().map(|(n, fd)| (*n, fd.clone())),
+                                    );
+                                    child.fds = process.fds;
+                                }
+                            }
+                            Entry::Vacant(entry) => {
+                                entry.insert(process);
+                            }
+                        }
+                    }
+                    "execve" | "execveat" => {
+                        // the file descriptor table is no longer shared after `execve`
+                        let process = self.get(pid);
+                        let mut fds = process.fds.borrow().clone();
+                        fds.retain(|_, fd| !fd.cloexec);
+                        process.fds = Rc::new(RefCell::new(fds));
+                    }
+                    "open" | "creat" | "openat" | "openat2" => {
+                        let fn_info = &FN_MAP[name];
+                        let path = args.iter().enumerate().find_map(|(i, arg)| match arg {
+                            StraceToken::String(path) => Some((i, decode_path(path))),
+                            _ => None,
+                        });
+                        if let Some((i, path)) = path {
+                            let path = self.resolve_arg(pid, fn_info, args, i, &path);
+                            let cloexec = fn_info
+                                .flags_arg
+                                .map(|i| has_flag(i, "O_CLOEXEC"))
+                                .unwrap_or(false);
+                            self.get(pid)
+                                .fds
+                                .borrow_mut()
+                                .insert(result, Fd { path, cloexec });
+                        }
+                    }
+                    "close" => {
+                        if let Some(fd) = arg(0) {
+                            self.get(pid).fds.borrow_mut().remove(&(fd as i32));
+                        }
+                    }
+                    "close_range" => {
+                        if let (Some(first), Some(last)) = (arg(0), arg(1)) {
+                            let (first, last) = (first as u32, last as u32);
+                            let cloexec = has_flag(2, "CLOSE_RANGE_CLOEXEC");
+                            let process = self.get(pid);
+                            let mut fds = process.fds.borrow_mut();
+                            fds.retain(|n, _| cloexec || *n < first as i32 || *n as u32 > last);
+                            fds.iter_mut()
+                                .filter(|(n, _)| (first..=last).contains(&(**n as u32)))
+                                .for_each(|(_, fd)| fd.cloexec = true);
+                        }
+                    }
+                    "dup" => self.dup(pid, arg(0), result, false),
+                    "dup2" => self.dup(pid, arg(0), result, false),
+                    "dup3" => self.dup(pid, arg(0), result, has_flag(2, "O_CLOEXEC")),
+                    "fcntl" | "fcntl64" => {
+                        if has_flag(1, "F_DUPFD") {
+                            self.dup(pid, arg(0), result, false);
+                        } else if has_flag(1, "F_DUPFD_CLOEXEC") {
+                            self.dup(pid, arg(0), result, true);
+                        } else if has_flag(1, "F_SETFD") {
+                            let cloexec = has_flag(2, "FD_CLOEXEC") || arg(2).unwrap_or(0) & 1 == 1;
+                            if let Some(fd) = arg(0) {
+                                if let Some(fd) =
+                                    self.get(pid).fds.borrow_mut().get_mut(&(fd as i32))
+                                {
+                                    fd.cloexec = cloexec;
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{decode_path, Process, Processes};
+    use crate::analysis::FN_MAP;
+    use crate::parse::{StraceLine, StraceToken};
+
+    fn update(processes: &mut Processes, lines: &[&str]) {
+        for line in lines {
+            processes.update(&StraceLine::from_str(line).unwrap());
+        }
+    }
+
+    /// Resolves the path in the argument at `index` of the given call
+    fn resolve(processes: &mut Processes, line: &str, index: usize) -> String {
+        let line = StraceLine::from_str(line).unwrap();
+        match &line.inner {
+            StraceToken::Call { name, args, .. } => {
+                let path = match &args[index] {
+                    StraceToken::String(path) => decode_path(path),
+                    _ => unreachable!(),
+                };
+                let path = processes.resolve_arg(line.pid, &FN_MAP[name], args, index, &path);
+                path.to_string_lossy().into_owned()
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn chdir_and_fork() {
+        let mut processes = Processes::new(Process::new("/home/user".into()), false);
+        update(
+            &mut processes,
+            &[
+                r#"chdir("project") = 0"#,
+                r#"clone(child_stack=NULL, flags=SIGCHLD) = 42"#,
+                r#"[pid 42] chdir("../other") = 0"#,
+                r#"[pid 43] chdir("/missing") = -1 ENOENT (No such file or directory)"#,
+            ],
+        );
+
+        assert_eq!(
+            *processes.get(None).cwd.borrow(),
+            Path::new("/home/user/project")
+        );
+        assert_eq!(
+            *processes.get(Some(42)).cwd.borrow(),
+            Path::new("/home/user/other")
+        );
+        assert_eq!(
+            *processes.get(Some(43)).cwd.borrow(),
+            Path::new("/home/user/project")
+        );
+        assert_eq!(
+            processes.resolve(Some(42), "./src/../main.rs"),
+            Path::new("/home/user/other/main.rs")
+        );
+    }
+
+    #[test]
+    fn chdir_in_threads() {
+        let mut processes = Processes::new(Process::new("/home/user".into()), false);
+        update(
+            &mut processes,
+            &[
+                r#"clone(child_stack=0x7f00, flags=CLONE_VM|CLONE_FS|CLONE_FILES|CLONE_SIGHAND|CLONE_THREAD|CLONE_SYSVSEM) = 42"#,
+                r#"clone(child_stack=0x7f00, flags=CLONE_VM|CLONE_FS) = 43"#,
+                r#"clone(child_stack=NULL, flags=SIGCHLD) = 44"#,
+                r#"[pid 42] chdir("project") = 0"#,
+                // the thread was seen before the call that created it returned
+                r#"[pid 45] chdir("src") = 0"#,
+                r#"clone3({flags=CLONE_VM|CLONE_FS|CLONE_FILES|CLONE_SIGHAND|CLONE_THREAD, exit_signal=0}, 88) = 45"#,
+            ],
+        );
+
+        // the working directory is shared with threads and with processes created with `CLONE_FS`
+        for pid in [None, Some(42), Some(43), Some(45)] {
+            assert_eq!(
+                processes.resolve(pid, "main.rs"),
+                Path::new("/home/user/project/src/main.rs")
+            );
+        }
+        assert_eq!(
+            processes.resolve(Some(44), "main.rs"),
+            Path::new("/home/user/main.rs")
+        );
+    }
+
+    #[test]
+    fn symlink_targets() {
+        let mut processes = Processes::new(Process::new("/home/user".into()), false);
+        let line = r#"symlink("../target", "dir/link") = 0"#;
+        assert_eq!(resolve(&mut processes, line, 0), "/home/user/target");
+        assert_eq!(resolve(&mut processes, line, 1), "/home/user/dir/link");
+    }
+
+    #[test]
+    fn dirfds() {
+        let mut processes = Processes::new(Process::new("/home/user".into()), false);
+        update(
+            &mut processes,
+            &[
+                r#"openat(AT_FDCWD, "/etc", O_RDONLY|O_DIRECTORY|O_CLOEXEC) = 3"#,
+                r#"openat(AT_FDCWD, "src", O_RDONLY|O_DIRECTORY) = 4"#,
+                r#"fcntl(4, F_DUPFD, 10) = 10"#,
+                r#"dup2(3, 5) = 5"#,
+                r#"close(4) = 0"#,
+            ],
+        );
+
+        assert_eq!(
+            resolve(
+                &mut processes,
+                r#"newfstatat(3, "passwd", 0x7ffc, 0) = 0"#,
+                1
+            ),
+            "/etc/passwd"
+        );
+        assert_eq!(
+            resolve(
+                &mut processes,
+                r#"openat(10, "../main.rs", O_RDONLY) = 4"#,
+                1
+            ),
+            "/home/user/main.rs"
+        );
+        assert_eq!(
+            resolve(
+                &mut processes,
+                r#"renameat2(5, "a", AT_FDCWD, "b", 0) = 0"#,
+                1
+            ),
+            "/etc/a"
+        );
+        assert_eq!(
+            resolve(
+                &mut processes,
+                r#"renameat2(5, "a", AT_FDCWD, "b", 0) = 0"#,
+                3
+            ),
+            "/home/user/b"
+        );
+        // closed file descriptors fall back to the working directory
+        assert_eq!(
+            resolve(&mut processes, r#"unlinkat(4, "tmp", 0) = 0"#, 1),
+            "/home/user/tmp"
+        );
+
+        // file descriptors are inherited, and those marked close-on-exec are closed by `execve`
+        update(
+            &mut processes,
+            &[
+                r#"fork() = 42"#,
+                r#"[pid 42] execve("/bin/true", ["true"], 0x7ffc /* 1 var */) = 0"#,
+            ],
+        );
+        assert_eq!(
+            resolve(
+                &mut processes,
+                r#"[pid 42] newfstatat(3, "passwd", 0x7ffc, 0) = 0"#,
+                1
+            ),
+            "/home/user/passwd"
+        );
+        assert_eq!(
+            resolve(
+                &mut processes,
+                r#"[pid 42] newfstatat(10, "lib.rs", 0x7ffc, 0) = 0"#,
+                1
+            ),
+            "/home/user/src/lib.rs"
+        );
+    }
+}
@@ -0,0 +1,56 @@
+const SIGNAL_NAMES: &[(i32, &str)] = &[
+    (libc::SIGHUP, "SIGHUP"),
+    (libc::SIGINT, "SIGINT"),
+    (libc::SIGQUIT, "SIGQUIT"),
+    (libc::SIGILL, "SIGILL"),
+    (libc::SIGTRAP, "SIGTRAP"),
+    (libc::SIGABRT, "SIGABRT"),
+    (libc::SIGBUS, "SIGBUS"),
+    (libc::SIGFPE, "SIGFPE"),
+    (libc::SIGKILL, "SIGKILL"),
+    (libc::SIGUSR1, "SIGUSR1"),
+    (libc::SIGSEGV, "SIGSEGV"),
+    (libc::SIGUSR2, "SIGUSR2"),
+    (libc::SIGPIPE, "SIGPIPE"),
+    (libc::SIGALRM, "SIGALRM"),
+    (libc::SIGTERM, "SIGTERM"),
+    (libc::SIGSTKFLT, "SIGSTKFLT"),
+    (libc::SIGCHLD, "SIGCHLD"),
+    (libc::SIGCONT, "SIGCONT"),
+    (libc::SIGSTOP, "SIGSTOP"),
+    (libc::SIGTSTP, "SIGTSTP"),
+    (libc::SIGTTIN, "SIGTTIN"),
+    (libc::SIGTTOU, "SIGTTOU"),
+    (libc::SIGURG, "SIGURG"),
+    (libc::SIGXCPU, "SIGXCPU"),
+    (libc::SIGXFSZ, "SIGXFSZ"),
+    (libc::SIGVTALRM, "SIGVTALRM"),
+    (libc::SIGPROF, "SIGPROF"),
+    (libc::SIGWINCH, "SIGWINCH"),
+    (libc::SIGIO, "SIGIO"),
+    (libc::SIGPWR, "SIGPWR"),
+    (libc::SIGSYS, "SIGSYS"),
+];
+
+/// Returns the symbolic name of a signal in the same format as `strace`, e.g.: `SIGINT` or `SIGRT_2`.
+/// Signals without a name (e.g.: those reserved by libc below `SIGRTMIN`) are returned as their number.
+pub fn name(signal: i32) -> String {
+    match SIGNAL_NAMES.iter().find(|(n, _)| *n == signal) {
+        Some((_, name)) => name.to_string(),
+        None if (libc::SIGRTMIN()..=libc::SIGRTMAX()).contains(&signal) => {
+            format!("SIGRT_{}", signal - libc::SIGRTMIN())
+        }
+        None => signal.to_string(),
+    }
+}
+
+/// Returns the number of a signal from its symbolic name, the inverse of `name`.
+pub fn number(name: &str) -> Option<i32> {
+    match SIGNAL_NAMES.iter().find(|(_, n)| *n == name) {
+        Some((signal, _)) => Some(*signal),
+        None => match name.strip_prefix("SIGRT_") {
+            Some(n) => n.parse::<i32>().ok().map(|n| n + libc::SIGRTMIN()),
+            None => name.parse().ok(),
+        },
+    }
+}
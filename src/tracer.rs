@@ -0,0 +1,775 @@
+use std::collections::HashSet;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fmt::{self, Display};
+use std::io::{BufRead, BufReader};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use globset::Glob;
+use regex::bytes::Regex;
+
+use crate::analysis::{AccessMode, FN_MAP};
+use crate::backend::{self, Backend};
+use crate::errno::Errno;
+use crate::filter::{AccessModes, ExcludeSet, ExePattern, FileTypes, PathFilter, ProcessFilter};
+use crate::fs;
+use crate::landlock::Ruleset;
+use crate::parse::stitch::{Stitched, Stitcher};
+use crate::parse::{string::decode_hex_bytes, CallInfo, CallResult, StraceLine, StraceToken};
+use crate::processes::{Process, Processes};
+use crate::signal;
+
+/// How many events may be queued before tracing waits for them to be consumed.
+const CHANNEL_BOUND: usize = 1024;
+
+/// A single access of a path by the traced program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileAccess {
+    /// The absolute path that was accessed (or relative, if `Tracer::relative_to` was used)
+    pub path: PathBuf,
+    /// The name of the syscall that accessed the path
+    pub syscall: String,
+    /// The pid of the process that made the syscall, if it's known
+    pub pid: Option<i32>,
+    /// When the syscall was made, since the Unix epoch
+    pub time: Option<Duration>,
+    /// The value returned by the syscall
+    pub result: CallResult,
+    /// Any information printed after the result, such as the description of an error, or `flags O_RDONLY` for
+    /// `fcntl(fd, F_GETFL)`
+    pub info: Option<String>,
+    /// How long the syscall took, if it was recorded (with `strace -T`)
+    pub duration: Option<Duration>,
+    /// Whether the syscall succeeded, if that can be determined
+    pub success: Option<bool>,
+    /// Whether the path was read, written or both, as far as can be told from the syscall and its flags
+    pub mode: AccessMode,
+}
+
+/// Something that happened in the traced program, as returned by `Tracer`.
+#[derive(Debug)]
+pub enum Event {
+    /// A path was accessed
+    Access(FileAccess),
+    /// A process (or thread) was created by another process
+    Spawn {
+        pid: i32,
+        parent: Option<i32>,
+        /// Whether it's a thread of its parent rather than a new process (i.e.: it was created with `CLONE_THREAD`)
+        thread: bool,
+    },
+    /// A process executed a program, which replaces the program it was running before
+    Exec {
+        pid: Option<i32>,
+        /// The absolute path of the program
+        path: PathBuf,
+        argv: Vec<OsString>,
+    },
+    /// A process (or thread) exited
+    Exit {
+        pid: Option<i32>,
+        status: ExitStatus,
+    },
+    /// A line of `strace` output couldn't be parsed, which usually means it was recorded with different options
+    InvalidLine { line: String, error: String },
+    /// Permission to attach to the given pid was denied
+    PermissionDenied(i32),
+}
+
+/// How the traced program exited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// It exited with the given code
+    Code(i32),
+    /// It was killed by the given signal
+    Signal(i32),
+}
+
+impl ExitStatus {
+    /// The exit code a shell would report for the program, which is 128 plus the signal if it was killed.
+    pub fn code(&self) -> i32 {
+        match self {
+            ExitStatus::Code(code) => *code,
+            ExitStatus::Signal(signal) => 128 + signal,
+        }
+    }
+}
+
+impl Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitStatus::Code(code) => write!(f, "exited with {}", code),
+            ExitStatus::Signal(signal) => write!(f, "killed by {}", signal::name(*signal)),
+        }
+    }
+}
+
+enum Target {
+    Command(Vec<String>),
+    Pid(usize),
+    Input(Box<dyn BufRead + Send>),
+}
+
+/// Builds a trace of a program, or of the recorded output of a previous `strace` run.
+///
+/// ```no_run
+/// use ftrace::{Event, Tracer};
+///
+/// let mut trace = Tracer::command(&["ls"]).no_duplicates(true).spawn()?;
+/// for event in &mut trace {
+///     if let Event::Access(access) = event {
+///         println!("{} {}", access.mode, access.path.display());
+///     }
+/// }
+/// trace.wait()?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct Tracer {
+    target: Target,
+    backend: Option<Backend>,
+    cwd: Option<PathBuf>,
+    relative_to: Option<PathBuf>,
+    file_types: Option<FileTypes>,
+    access_modes: Option<AccessModes>,
+    errnos: Option<HashSet<Errno>>,
+    process_filter: ProcessFilter,
+    path_filter: PathFilter,
+    non_existent: bool,
+    no_duplicates: bool,
+    sandbox: Option<Ruleset>,
+}
+
+impl Tracer {
+    fn new(target: Target) -> Tracer {
+        Tracer {
+            target,
+            backend: None,
+            cwd: None,
+            relative_to: None,
+            file_types: None,
+            access_modes: None,
+            errnos: None,
+            process_filter: ProcessFilter::default(),
+            path_filter: PathFilter::default(),
+            non_existent: false,
+            no_duplicates: false,
+            sandbox: None,
+        }
+    }
+
+    /// Traces the given command, which is executed directly rather than by a shell.
+    pub fn command<I, S>(cmd: I) -> Tracer
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Tracer::new(Target::Command(
+            cmd.into_iter()
+                .map(|arg| arg.as_ref().to_string())
+                .collect(),
+        ))
+    }
+
+    /// Traces an already running process (note this requires elevated privileges).
+    pub fn pid(pid: usize) -> Tracer {
+        Tracer::new(Target::Pid(pid))
+    }
+
+    /// Reads existing `strace` output rather than tracing a program.
+    pub fn input<R: BufRead + Send + 'static>(reader: R) -> Tracer {
+        Tracer::new(Target::Input(Box::new(reader)))
+    }
+
+    /// Which backend to use, defaults to `strace` if it's installed and `ptrace` otherwise.
+    pub fn backend(mut self, backend: Backend) -> Tracer {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// The working directory of the program when its output was recorded (only used with `Tracer::input`).
+    pub fn cwd(mut self, cwd: impl Into<PathBuf>) -> Tracer {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Makes paths relative to this directory rather than absolute.
+    pub fn relative_to(mut self, dir: impl Into<PathBuf>) -> Tracer {
+        self.relative_to = Some(dir.into());
+        self
+    }
+
+    /// Only include paths of the given types.
+    pub fn file_types(mut self, file_types: FileTypes) -> Tracer {
+        self.file_types = Some(file_types);
+        self
+    }
+
+    /// Only include paths that were accessed with the given modes.
+    pub fn access_modes(mut self, access_modes: AccessModes) -> Tracer {
+        self.access_modes = Some(access_modes);
+        self
+    }
+
+    /// Only include paths that the program failed to access with one of the given errors (e.g.: `EACCES`).
+    pub fn errnos(mut self, errnos: impl IntoIterator<Item = Errno>) -> Tracer {
+        self.errnos = Some(errnos.into_iter().collect());
+        self
+    }
+
+    /// Only include paths that match one of the given globs (or one of the `regexes`).
+    pub fn include(mut self, globs: impl IntoIterator<Item = Glob>) -> Tracer {
+        let globs = globs.into_iter().map(|glob| glob.compile_matcher());
+        self.path_filter.include.extend(globs);
+        self
+    }
+
+    /// Exclude paths that match any of the given globs.
+    pub fn exclude(mut self, globs: impl IntoIterator<Item = Glob>) -> Tracer {
+        let globs = globs.into_iter().map(|glob| glob.compile_matcher());
+        self.path_filter.exclude.extend(globs);
+        self
+    }
+
+    /// Exclude the paths in a built-in set, e.g.: `ExcludeSet::System`.
+    pub fn exclude_set(self, set: ExcludeSet) -> Tracer {
+        // NOTE: the globs of each set are known to be valid
+        self.exclude(set.globs().iter().map(|glob| Glob::new(glob).unwrap()))
+    }
+
+    /// Only include paths that match one of the given regular expressions (or one of the `include` globs).
+    pub fn regexes(mut self, regexes: impl IntoIterator<Item = Regex>) -> Tracer {
+        self.path_filter.regexes.extend(regexes);
+        self
+    }
+
+    /// Only include paths accessed by the processes with the given pids.
+    pub fn only_pids(mut self, pids: impl IntoIterator<Item = i32>) -> Tracer {
+        self.process_filter.only_pids.extend(pids);
+        self
+    }
+
+    /// Only include paths accessed by processes running a program that matches one of the given patterns.
+    pub fn only_exes(mut self, patterns: impl IntoIterator<Item = ExePattern>) -> Tracer {
+        self.process_filter.only_exes.extend(patterns);
+        self
+    }
+
+    /// Exclude paths accessed by processes running a program that matches any of the given patterns.
+    pub fn exclude_exes(mut self, patterns: impl IntoIterator<Item = ExePattern>) -> Tracer {
+        self.process_filter.exclude_exes.extend(patterns);
+        self
+    }
+
+    /// Also include (or exclude) the descendants of the processes matched by `only_pids`, `only_exes` and
+    /// `exclude_exes`, even once they've executed another program.
+    pub fn descendants(mut self, descendants: bool) -> Tracer {
+        self.process_filter.descendants = descendants;
+        self
+    }
+
+    /// Include paths that the program attempted to access but failed to (e.g.: because they didn't exist).
+    pub fn non_existent(mut self, non_existent: bool) -> Tracer {
+        self.non_existent = non_existent;
+        self
+    }
+
+    /// Only include each path the first time it's accessed.
+    pub fn no_duplicates(mut self, no_duplicates: bool) -> Tracer {
+        self.no_duplicates = no_duplicates;
+        self
+    }
+
+    /// Restricts the traced command with a Landlock ruleset, which is applied just before it's executed.
+    /// This requires the `ptrace` backend, since `strace` executes the command itself.
+    pub fn sandbox(mut self, ruleset: Ruleset) -> Tracer {
+        self.sandbox = Some(ruleset);
+        self
+    }
+
+    /// Starts tracing in the background, returning a `Trace` which yields events as they happen.
+    pub fn spawn(self) -> Result<Trace> {
+        if let Target::Command(cmd) = &self.target {
+            if cmd.is_empty() {
+                return Err(anyhow!("No command was given to trace"));
+            }
+        }
+
+        let current_dir = env::current_dir().context("Failed to get the current directory")?;
+        let relative_to = self
+            .relative_to
+            .map(|dir| fs::normalize(&current_dir.join(dir)));
+
+        let cwd = self.cwd.map(|cwd| fs::normalize(&current_dir.join(cwd)));
+
+        let backend = match &self.target {
+            Target::Input(_) => None,
+            _ => Some(self.backend.unwrap_or_else(Backend::detect)),
+        };
+        log::trace!("using backend: {:?}", backend);
+        let strace_path = match backend {
+            Some(Backend::Strace) => {
+                Some(backend::strace::find().context("Failed to find `strace` binary")?)
+            }
+            _ => None,
+        };
+        if self.sandbox.is_some() && backend != Some(Backend::Ptrace) {
+            return Err(anyhow!(
+                "A sandbox can only be used with the ptrace backend"
+            ));
+        }
+
+        let (sender, receiver) = mpsc::sync_channel(CHANNEL_BOUND);
+        let (target, file_types, access_modes) = (self.target, self.file_types, self.access_modes);
+        let (errnos, process_filter, path_filter) =
+            (self.errnos, self.process_filter, self.path_filter);
+        let (non_existent, no_duplicates) = (self.non_existent, self.no_duplicates);
+        let sandbox = self.sandbox;
+        let handle = thread::spawn(move || -> Result<Option<ExitStatus>> {
+            // the state of the traced program when it starts (or when it's attached to)
+            // NOTE: this isn't `Send`, so it's created on the thread that does the tracing
+            let (initial, live) = match (&target, cwd) {
+                (Target::Input(_), Some(cwd)) => (Process::new(cwd), false),
+                (Target::Input(_), None) => (Process::new(current_dir), false),
+                (Target::Pid(pid), _) => (
+                    Process::from_proc(*pid as i32).unwrap_or_else(|| Process::new(current_dir)),
+                    true,
+                ),
+                (Target::Command(_), _) => (Process::new(current_dir), true),
+            };
+            let root = match &target {
+                Target::Pid(pid) => Some(Some(*pid as i32)),
+                _ => None,
+            };
+            let mut analyser = Analyser {
+                sender,
+                processes: Processes::new(initial, live),
+                stitcher: Stitcher::default(),
+                root,
+                exit: None,
+                seen: HashSet::new(),
+                relative_to,
+                file_types,
+                access_modes,
+                errnos,
+                process_filter,
+                path_filter,
+                included: HashSet::new(),
+                excluded: HashSet::new(),
+                non_existent,
+                no_duplicates,
+            };
+
+            let (cmd, pid) = match target {
+                Target::Input(reader) => {
+                    analyser
+                        .handle_lines(reader)
+                        .context("Failed to read input")?;
+                    return Ok(analyser.exit);
+                }
+                Target::Command(cmd) => (cmd, None),
+                Target::Pid(pid) => (vec![], Some(pid)),
+            };
+
+            match strace_path {
+                Some(strace_path) => {
+                    let mut strace = backend::strace::spawn(strace_path, &cmd, pid)?;
+                    let stderr = strace.child.stderr.take();
+                    let (output, handle) = strace.wait();
+                    analyser.handle_lines(BufReader::new(output))?;
+                    // when attaching to a process, `strace` reports whether it could attach via STDERR
+                    // NOTE: this is read after the trace, which is fine since `strace` only prints a few messages
+                    if let Some(stderr) = stderr {
+                        analyser.handle_lines(BufReader::new(stderr))?;
+                    }
+
+                    let exit_status = handle
+                        .join()
+                        .map_err(|_| anyhow!("The thread waiting for strace panicked"))?
+                        .context("An error occurred while waiting for process to end")?;
+                    let msg = format!(
+                        "strace exited with code: {}",
+                        exit_status
+                            .code()
+                            .map(|c| c.to_string())
+                            .unwrap_or_else(|| "???".to_string())
+                    );
+                    // NOTE: `strace` exits with the same status as the traced program, so only fail if that wasn't seen
+                    if !exit_status.success() && analyser.exit.is_none() {
+                        return Err(anyhow!(msg));
+                    }
+
+                    log::trace!("{}", msg);
+                    Ok(analyser.exit)
+                }
+                None => {
+                    let sandbox = sandbox.as_ref();
+                    backend::ptrace::trace(&cmd, pid, sandbox, |strace| {
+                        analyser.handle_line(strace)
+                    })
+                    .context("Failed to trace process")?;
+                    Ok(analyser.exit)
+                }
+            }
+        });
+
+        Ok(Trace { receiver, handle })
+    }
+}
+
+/// A running trace, which is an iterator of the events that happen while tracing.
+pub struct Trace {
+    receiver: Receiver<Event>,
+    handle: JoinHandle<Result<Option<ExitStatus>>>,
+}
+
+impl Trace {
+    /// Waits for tracing to finish, returning how the traced program exited (if that was seen) or any error that
+    /// occurred. Any events that haven't been consumed are discarded.
+    pub fn wait(self) -> Result<Option<ExitStatus>> {
+        drop(self.receiver);
+        match self.handle.join() {
+            Ok(result) => result,
+            Err(_) => Err(anyhow!("The tracing thread panicked")),
+        }
+    }
+}
+
+impl Iterator for Trace {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// Turns the lines of a trace into events.
+struct Analyser {
+    sender: SyncSender<Event>,
+    processes: Processes,
+    stitcher: Stitcher,
+    /// The pid of the traced program, which is set by the first line of the trace (and may be `None`, since `strace`
+    /// doesn't always prefix the lines of the initial process with a pid)
+    root: Option<Option<i32>>,
+    /// How the traced program exited
+    exit: Option<ExitStatus>,
+    seen: HashSet<PathBuf>,
+    relative_to: Option<PathBuf>,
+    file_types: Option<FileTypes>,
+    access_modes: Option<AccessModes>,
+    errnos: Option<HashSet<Errno>>,
+    process_filter: ProcessFilter,
+    path_filter: PathFilter,
+    /// Processes that are included (or excluded) because of one of their ancestors, or because they're threads
+    included: HashSet<Option<i32>>,
+    excluded: HashSet<Option<i32>>,
+    non_existent: bool,
+    no_duplicates: bool,
+}
+
+impl Analyser {
+    /// Sends an event, returning `false` if nothing is listening for events anymore.
+    fn send(&self, event: Event) -> bool {
+        self.sender.send(event).is_ok()
+    }
+
+    /// Handles each line of `strace` output until the end of the reader, or until nothing is listening for events.
+    fn handle_lines(&mut self, reader: impl BufRead) -> Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            log::trace!("RAW LINE: {}", line);
+            if !self.handle_raw_line(line) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses and handles a single line of `strace` output, returning `false` if tracing should stop.
+    fn handle_raw_line(&mut self, line: String) -> bool {
+        let strace = match StraceLine::from_str(&line) {
+            Ok(strace) => strace,
+            Err(e) => {
+                log::warn!("INVALID LINE: {}", line);
+                let error = e.to_string();
+                return self.send(Event::InvalidLine { line, error });
+            }
+        };
+
+        match self.stitcher.stitch(&strace) {
+            Stitched::Complete => self.handle_line(&strace),
+            Stitched::Unfinished => true,
+            Stitched::Resumed { pid, time, call } => match StraceLine::from_str(&call) {
+                Ok(mut strace) => {
+                    strace.pid = pid;
+                    strace.time = time;
+                    self.handle_line(&strace)
+                }
+                Err(e) => {
+                    log::warn!("INVALID RESUMED LINE: {}", call);
+                    let error = e.to_string();
+                    self.send(Event::InvalidLine { line, error })
+                }
+            },
+            Stitched::Orphaned => {
+                log::warn!("ORPHANED LINE: {}", line);
+                true
+            }
+        }
+    }
+
+    /// Handles a single line, returning `false` if tracing should stop.
+    fn handle_line(&mut self, strace: &StraceLine) -> bool {
+        log::debug!("PARSED LINE: {}", strace);
+        if let StraceToken::PermissionDenied(_, pid) = strace.inner {
+            self.send(Event::PermissionDenied(pid));
+            return false;
+        }
+
+        if !self.handle_process(strace) {
+            return false;
+        }
+
+        // call expressions without results are inline call expressions, so skip them
+        if let StraceToken::Call {
+            name,
+            args,
+            result: Some(result),
+            info,
+        } = &strace.inner
+        {
+            if !self.handle_call(strace, name, args, *result, *info) {
+                return false;
+            }
+        }
+
+        self.processes.update(strace);
+
+        true
+    }
+
+    /// Sends events when processes are created, execute programs or exit, and records how the traced program exited.
+    fn handle_process(&mut self, strace: &StraceLine) -> bool {
+        let pid = strace.pid;
+        let event = match &strace.inner {
+            StraceToken::Exit(code) => Some(Event::Exit {
+                pid,
+                status: ExitStatus::Code(*code),
+            }),
+            StraceToken::Kill(name) => signal::number(name).map(|signal| Event::Exit {
+                pid,
+                status: ExitStatus::Signal(signal),
+            }),
+            StraceToken::Call {
+                name,
+                args,
+                result: Some(CallResult::Int(result)),
+                ..
+            } => match *name {
+                "clone" | "clone3" | "fork" | "vfork" if *result > 0 => Some(Event::Spawn {
+                    pid: *result as i32,
+                    parent: pid,
+                    thread: args
+                        .iter()
+                        .any(|arg| arg.idents().contains(&"CLONE_THREAD")),
+                }),
+                // NOTE: `execve` only returns if it failed, but `strace` prints the result of a successful call as 0
+                "execve" | "execveat" if *result == 0 => self.exec(pid, name, args),
+                _ => None,
+            },
+            StraceToken::Call { .. } => None,
+            _ => return true,
+        };
+
+        match &event {
+            Some(Event::Spawn {
+                pid: child, thread, ..
+            }) => self.inherit(pid, Some(*child), *thread),
+            Some(Event::Exit { .. }) => {
+                self.included.remove(&pid);
+                self.excluded.remove(&pid);
+            }
+            _ => {}
+        }
+
+        // the first process to appear in the trace is the traced program
+        let root = *self.root.get_or_insert(pid);
+        match event {
+            Some(Event::Exit { status, .. }) if pid == root => {
+                self.exit = Some(status);
+                self.send(Event::Exit { pid, status })
+            }
+            Some(event) => self.send(event),
+            None => true,
+        }
+    }
+
+    /// Returns the program and arguments of a successful `execve` or `execveat` call.
+    fn exec(&mut self, pid: Option<i32>, name: &str, args: &[StraceToken]) -> Option<Event> {
+        let decode = |s: &str| OsString::from_vec(decode_hex_bytes(s));
+        let (i, path) = args.iter().enumerate().find_map(|(i, arg)| match arg {
+            StraceToken::String(path) => Some((i, decode(path))),
+            _ => None,
+        })?;
+        let argv = match args.get(i + 1) {
+            Some(StraceToken::Array(argv)) => argv
+                .iter()
+                .filter_map(|arg| match arg {
+                    StraceToken::String(arg) => Some(decode(arg)),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        // NOTE: this is set here rather than when the processes are updated, so the access of the program itself is
+        // attributed to it
+        let path = self
+            .processes
+            .resolve_arg(pid, &FN_MAP[name], args, i, path);
+        self.processes.get(pid).exe = Some(path.clone());
+
+        Some(Event::Exec { pid, path, argv })
+    }
+
+    /// Whether a process matches the process filters (including because of its ancestors), and whether it's excluded.
+    fn process_matches(&mut self, pid: Option<i32>) -> (bool, bool) {
+        let exe = self.processes.get(pid).exe.as_deref();
+        let filter = &self.process_filter;
+        (
+            self.included.contains(&pid) || filter.matches_only(pid, exe),
+            self.excluded.contains(&pid) || filter.matches_exclude(exe),
+        )
+    }
+
+    /// Passes on whether a process is included (or excluded) to its child, if it's a thread or if descendants are
+    /// being filtered too.
+    fn inherit(&mut self, parent: Option<i32>, child: Option<i32>, thread: bool) {
+        if !thread && !self.process_filter.descendants {
+            return;
+        }
+
+        let (included, excluded) = self.process_matches(parent);
+        if included {
+            self.included.insert(child);
+        }
+        if excluded {
+            self.excluded.insert(child);
+        }
+    }
+
+    /// Whether the paths accessed by a process are included by the process filters.
+    fn is_process_included(&mut self, pid: Option<i32>) -> bool {
+        let (included, excluded) = self.process_matches(pid);
+        !excluded && (included || !self.process_filter.is_only())
+    }
+
+    fn handle_call(
+        &mut self,
+        strace: &StraceLine,
+        name: &str,
+        args: &[StraceToken],
+        result: CallResult,
+        info: Option<CallInfo>,
+    ) -> bool {
+        if !self.is_process_included(strace.pid) {
+            return true;
+        }
+
+        let fn_info = &FN_MAP[name];
+        let mode = fn_info.access_mode(args);
+        if let Some(access_modes) = &self.access_modes {
+            if !access_modes.matches(mode) {
+                return true;
+            }
+        }
+
+        let success = fn_info.did_succeed(&result);
+        match (&self.errnos, result) {
+            // NOTE: filtering by error implies that failed accesses are included
+            (Some(errnos), CallResult::Error(errno)) if errnos.contains(&errno) => {}
+            (Some(_), _) => return true,
+            (None, _) if success == Some(false) && !self.non_existent => return true,
+            (None, _) => {}
+        }
+
+        // NOTE: handle special case for `execve`: the first argument is the binary being executed, and
+        // the second argument is the binary's `argv` (which does not contain paths for file accesses)
+        let mut maybe_paths = if name == "execve" {
+            if let Some(StraceToken::String(s)) = args.first() {
+                vec![(0, *s)]
+            } else {
+                vec![]
+            }
+        } else if fn_info.fd_arg.is_some() {
+            // NOTE: calls that access a file by its file descriptor only have strings of data (e.g.: `read`)
+            vec![]
+        } else {
+            args.iter()
+                .enumerate()
+                .flat_map(|(i, arg)| arg.strs().into_iter().map(move |s| (i, s)))
+                .collect()
+        }
+        .into_iter()
+        .map(|(i, s)| (i, PathBuf::from(OsStr::from_bytes(&decode_hex_bytes(s)))))
+        .filter(|(_, path)| !path.as_os_str().is_empty())
+        .collect::<Vec<_>>();
+
+        // some calls only refer to the file they access by its file descriptor
+        if let Some(i) = fn_info.fd_arg {
+            let pid = strace.pid;
+            if let Some(path) = args
+                .get(i)
+                .and_then(|fd| self.processes.fd_arg_path(pid, fd))
+            {
+                maybe_paths.push((i, path));
+            }
+        }
+
+        for (i, path) in maybe_paths {
+            let path = self
+                .processes
+                .resolve_arg(strace.pid, fn_info, args, i, path);
+            if !self.path_filter.matches(&path) {
+                continue;
+            }
+            if let Some(file_types) = &self.file_types {
+                if !file_types.matches(&path) {
+                    continue;
+                }
+            }
+
+            let path = match &self.relative_to {
+                Some(dir) => fs::relative_to(&path, dir),
+                None => path,
+            };
+
+            // Skip duplicates if set
+            if self.no_duplicates && !self.seen.insert(path.clone()) {
+                continue;
+            }
+
+            let access = FileAccess {
+                path,
+                syscall: name.to_string(),
+                pid: strace.pid,
+                time: strace.time,
+                result,
+                info: info.and_then(|info| info.comment).map(str::to_string),
+                duration: info.and_then(|info| info.duration),
+                success,
+                mode,
+            };
+            if !self.send(Event::Access(access)) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crate::tracer::{Event, ExitStatus, FileAccess};
+
+/// A process in a `ProcessTree`, along with the paths it accessed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessNode {
+    /// The pid of the process, which is `None` for the traced program when `strace` doesn't print it
+    pub pid: Option<i32>,
+    /// The index of the process that created this one
+    pub parent: Option<usize>,
+    /// The indices of the processes that this one created
+    pub children: Vec<usize>,
+    /// The program that the process executed, if it called `execve`
+    pub exe: Option<PathBuf>,
+    /// The arguments of the last program the process executed, which is empty if it never called `execve`
+    pub argv: Vec<OsString>,
+    /// How the process exited, which is `None` if it was still running when tracing stopped
+    pub exit: Option<ExitStatus>,
+    /// The paths accessed by the process and all of its threads
+    pub accesses: Vec<FileAccess>,
+}
+
+impl ProcessNode {
+    /// Returns the command line of the process, or its executable if it had no arguments.
+    pub fn command(&self) -> Option<String> {
+        if self.argv.is_empty() {
+            return self
+                .exe
+                .as_ref()
+                .map(|exe| exe.to_string_lossy().into_owned());
+        }
+
+        let argv = self.argv.iter().map(|arg| arg.to_string_lossy());
+        Some(argv.collect::<Vec<_>>().join(" "))
+    }
+}
+
+/// The hierarchy of processes created while tracing, built from the events of a `Trace`.
+///
+/// When a process executes a program after it's already executed one (e.g.: `sh -c 'exec make'`) it's given a new node,
+/// so the accesses of each program are kept separate.
+#[derive(Debug, Default)]
+pub struct ProcessTree {
+    nodes: Vec<ProcessNode>,
+    /// The index of the node of each pid (threads share the node of their process)
+    pids: HashMap<Option<i32>, usize>,
+}
+
+impl ProcessTree {
+    /// Returns all processes, in the order they were first seen.
+    pub fn nodes(&self) -> &[ProcessNode] {
+        &self.nodes
+    }
+
+    /// Returns the indices of the processes without a parent, which is usually just the traced program.
+    pub fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.nodes.len()).filter(move |i| self.nodes[*i].parent.is_none())
+    }
+
+    /// Returns the index of the node of the given pid, creating it if it hasn't been seen before.
+    fn node(&mut self, pid: Option<i32>) -> usize {
+        match self.pids.get(&pid) {
+            Some(i) => *i,
+            None => self.push(pid, None),
+        }
+    }
+
+    /// Adds a new node for the given pid, replacing any previous node it had.
+    fn push(&mut self, pid: Option<i32>, parent: Option<usize>) -> usize {
+        let i = self.nodes.len();
+        self.nodes.push(ProcessNode {
+            pid,
+            parent,
+            ..ProcessNode::default()
+        });
+        self.pids.insert(pid, i);
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(i);
+        }
+
+        i
+    }
+
+    /// Updates the tree with an event from a `Trace`.
+    pub fn update(&mut self, event: &Event) {
+        match event {
+            Event::Access(access) => {
+                let i = self.node(access.pid);
+                self.nodes[i].accesses.push(access.clone());
+            }
+            Event::Spawn {
+                pid,
+                parent,
+                thread,
+            } => {
+                let parent = self.node(*parent);
+                match self.pids.get(&Some(*pid)).copied() {
+                    // threads are part of their parent's process, although they may have been seen before they were
+                    // created
+                    Some(i) if *thread => {
+                        let accesses = std::mem::take(&mut self.nodes[i].accesses);
+                        self.nodes[parent].accesses.extend(accesses);
+                        self.pids.insert(Some(*pid), parent);
+                    }
+                    None if *thread => {
+                        self.pids.insert(Some(*pid), parent);
+                    }
+                    // the child was seen before the call that created it returned
+                    Some(i) if self.nodes[i].exit.is_none() => {
+                        if self.nodes[i].parent.is_none() && i != parent {
+                            self.nodes[i].parent = Some(parent);
+                            self.nodes[parent].children.push(i);
+                        }
+                    }
+                    // NOTE: pids are reused once a process has exited
+                    _ => {
+                        self.push(Some(*pid), Some(parent));
+                    }
+                }
+            }
+            Event::Exec { pid, path, argv } => {
+                let mut i = self.node(*pid);
+                if self.nodes[i].exe.is_some() {
+                    i = self.push(*pid, self.nodes[i].parent);
+                }
+
+                self.nodes[i].exe = Some(path.clone());
+                self.nodes[i].argv = argv.clone();
+            }
+            Event::Exit { pid, status } => {
+                let i = self.node(*pid);
+                // threads exit too, but only the exit of the process itself is interesting
+                if self.nodes[i].pid == *pid {
+                    self.nodes[i].exit = Some(*status);
+                }
+            }
+            Event::InvalidLine { .. } | Event::PermissionDenied(_) => {}
+        }
+    }
+}
@@ -0,0 +1,947 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/ls.strace");
+
+fn ftrace_status(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ftrace"))
+        .args(args)
+        // ignore the user's config file
+        .env("XDG_CONFIG_HOME", "/nonexistent")
+        .output()
+        .unwrap()
+}
+
+fn ftrace(args: &[&str]) -> String {
+    let output = ftrace_status(args);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Writes the given calls to `trace.strace` in `dir`, printing every byte of each string in hexadecimal in the same
+/// way as `strace --strings-in-hex`.
+fn hex_fixture(dir: &Path, calls: &[impl AsRef<[u8]>]) -> PathBuf {
+    let mut trace = vec![];
+    for call in calls {
+        for (i, part) in call.as_ref().split(|b| *b == b'"').enumerate() {
+            if i > 0 {
+                trace.push(b'"');
+            }
+            // every other part is between quotes
+            match i % 2 {
+                0 => trace.extend(part),
+                _ => trace.extend(
+                    part.iter()
+                        .flat_map(|b| format!("\\x{:02x}", b).into_bytes()),
+                ),
+            }
+        }
+        trace.push(b'\n');
+    }
+
+    let path = dir.join("trace.strace");
+    fs::write(&path, trace).unwrap();
+    path
+}
+
+fn lines(output: &str) -> Vec<&str> {
+    output.lines().filter(|l| !l.is_empty()).collect()
+}
+
+#[test]
+fn replay_file() {
+    assert_eq!(
+        lines(&ftrace(&["--input", FIXTURE])),
+        vec![
+            r#""/usr/bin/ls""#,
+            r#""/etc/ld.so.cache""#,
+            r#""/lib/x86_64-linux-gnu/libc.so.6""#,
+            r#""/""#,
+            r#""/""#,
+            r#""/etc/ld.so.cache""#,
+        ]
+    );
+}
+
+#[test]
+fn replay_non_existent_no_duplicates() {
+    assert_eq!(
+        lines(&ftrace(&[
+            "--input",
+            FIXTURE,
+            "--non-existent",
+            "--no-duplicates"
+        ])),
+        vec![
+            r#""/usr/bin/ls""#,
+            r#""/etc/ld.so.preload""#,
+            r#""/etc/ld.so.cache""#,
+            r#""/lib/x86_64-linux-gnu/libc.so.6""#,
+            r#""/""#,
+        ]
+    );
+}
+
+#[test]
+fn replay_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ftrace"))
+        .args(["--input", "-"])
+        .env("XDG_CONFIG_HOME", "/nonexistent")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(std::fs::read(FIXTURE).unwrap().as_slice())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        lines(&String::from_utf8(output.stdout).unwrap()),
+        lines(&ftrace(&["--input", FIXTURE]))
+    );
+}
+
+#[test]
+fn replay_access_mode() {
+    const CC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/cc.strace");
+    assert_eq!(
+        lines(&ftrace(&[
+            "--input",
+            CC,
+            "--cwd",
+            "/src",
+            "--access",
+            "w",
+            "--show-mode"
+        ])),
+        vec![
+            r#"rw "/src/main.o""#,
+            r#"w  "/src/build.log""#,
+            r#"w  "/tmp/ccXyZ123.s""#,
+        ]
+    );
+    assert_eq!(
+        lines(&ftrace(&[
+            "--input", CC, "--cwd", "/src", "-a", "rw", "-a", "?"
+        ])),
+        vec![r#""/src/main.o""#]
+    );
+}
+
+#[test]
+fn replay_working_directory() {
+    const MAKE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/make.strace");
+    assert_eq!(
+        lines(&ftrace(&["--input", MAKE, "--cwd", "/src"])),
+        vec![
+            r#""/usr/bin/make""#,
+            r#""/src/sub""#,
+            r#""/src/sub/Makefile""#,
+            r#""/src/lib""#,
+            r#""/src/lib/lib.c""#,
+            r#""/src/sub/out.txt""#,
+        ]
+    );
+    assert_eq!(
+        lines(&ftrace(&[
+            "--input",
+            MAKE,
+            "--cwd",
+            "/src",
+            "--relative-to",
+            "/src/sub"
+        ])),
+        vec![
+            r#""../../usr/bin/make""#,
+            r#"".""#,
+            r#""Makefile""#,
+            r#""../lib""#,
+            r#""../lib/lib.c""#,
+            r#""out.txt""#,
+        ]
+    );
+}
+
+#[test]
+fn replay_jsonl() {
+    const CC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/cc.strace");
+    let output = ftrace(&["--input", CC, "--cwd", "/src", "-e", "--format", "jsonl"]);
+    let events = lines(&output)
+        .into_iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        events[3],
+        serde_json::json!({
+            "version": 1,
+            "path": "/src/main.c",
+            "path_bytes": "2f7372632f6d61696e2e63",
+            "syscall": "openat",
+            "pid": null,
+            "timestamp_us": 1611916300000300u64,
+            "result": 3,
+            "errno": null,
+            "duration_us": null,
+            "success": true,
+            "mode": "r"
+        })
+    );
+    assert_eq!(events[5]["path"], "/usr/include/missing.h");
+    assert_eq!(events[5]["errno"], "ENOENT (No such file or directory)");
+    assert_eq!(events[5]["success"], false);
+}
+
+#[test]
+fn replay_decoded_fds() {
+    const FDS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/fds.strace");
+    assert_eq!(
+        lines(&ftrace(&["--input", FDS, "--show-mode"])),
+        vec![
+            r#"r  "/usr/bin/cat""#,
+            r#"r  "/src/in.txt""#,
+            r#"r  "/src/in.txt""#,
+            r#"r  "/src/in.txt""#,
+            r#"r  "/src/in.txt""#,
+            r#"w  "/dev/pts/0""#,
+            // the file descriptor wasn't decoded, so it's looked up from the `openat` call
+            r#"w  "/src/in.txt""#,
+        ]
+    );
+}
+
+#[test]
+fn replay_fds_opened_before_attach() {
+    let dir = tempfile::tempdir().unwrap();
+    // e.g.: with `--pid`, files opened before attaching are only seen through the calls that use them
+    let fixture = hex_fixture(
+        dir.path(),
+        &[
+            r#"read(3</data/db>, "hello", 131072) = 5"#,
+            r#"mmap(NULL, 8192, PROT_READ|PROT_EXEC, MAP_PRIVATE, 4</usr/lib/libfoo.so>, 0) = 0x7f1c2a4b5000"#,
+            r#"write(5</data/log>, "x", 1) = 1"#,
+        ],
+    );
+    assert_eq!(
+        lines(&ftrace(&[
+            "--input",
+            fixture.to_str().unwrap(),
+            "--show-mode"
+        ])),
+        vec![
+            r#"r  "/data/db""#,
+            r#"r  "/usr/lib/libfoo.so""#,
+            r#"w  "/data/log""#,
+        ]
+    );
+}
+
+#[test]
+fn replay_errno() {
+    const CC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/cc.strace");
+    assert_eq!(
+        lines(&ftrace(&[
+            "--input",
+            CC,
+            "--cwd",
+            "/src",
+            "--errno",
+            "EACCES,ENOENT"
+        ])),
+        vec![r#""/usr/include/missing.h""#]
+    );
+    assert_eq!(
+        lines(&ftrace(&[
+            "--input", CC, "--errno", "EACCES", "--errno", "1"
+        ])),
+        Vec::<&str>::new()
+    );
+}
+
+#[test]
+fn replay_unfinished_calls() {
+    const THREADS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/threads.strace");
+    assert_eq!(
+        lines(&ftrace(&["--input", THREADS, "--show-mode", "--invalid"])),
+        vec![
+            r#"r  "/usr/bin/server""#,
+            r#"r  "/etc/server.conf""#,
+            r#"w  "/var/log/server.log""#,
+            r#"r  "/etc/server.conf""#,
+        ]
+    );
+}
+
+#[test]
+fn replay_process_tree() {
+    const PIPELINE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/pipeline.strace"
+    );
+    assert_eq!(
+        lines(&ftrace(&["--input", PIPELINE, "--cwd", "/src", "--tree"])),
+        vec![
+            "100 sh -c cc -c main.c && exec tee log (1 path)",
+            "└── 101 cc -c main.c (3 paths, exited with 0)",
+            "100 tee log (2 paths, killed by SIGPIPE)",
+        ]
+    );
+    assert_eq!(
+        lines(&ftrace(&[
+            "--input",
+            PIPELINE,
+            "--cwd",
+            "/src",
+            "--group-by",
+            "process",
+            "--access",
+            "w",
+        ])),
+        vec![
+            "101 cc -c main.c",
+            r#"  "/src/main.o""#,
+            "100 tee log",
+            r#"  "/src/log""#,
+        ]
+    );
+}
+
+#[test]
+fn replay_process_filters() {
+    const PIPELINE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/pipeline.strace"
+    );
+    let filter = |args: &[&str]| {
+        let args = [&["--input", PIPELINE, "--cwd", "/src"], args].concat();
+        lines(&ftrace(&args))
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+
+    let cc = vec![r#""/usr/bin/cc""#, r#""/src/main.c""#, r#""/src/main.o""#];
+    assert_eq!(filter(&["--only-exe", "cc"]), cc);
+    assert_eq!(filter(&["--only-exe", "/usr/*/c?"]), cc);
+    assert_eq!(filter(&["--only-pid", "101"]), cc);
+    assert_eq!(
+        filter(&["--only-exe", "tee"]),
+        vec![r#""/usr/bin/tee""#, r#""/src/log""#]
+    );
+    assert_eq!(
+        filter(&["--exclude-exe", "cc", "--exclude-exe", "tee"]),
+        vec![r#""/bin/sh""#]
+    );
+    // the thread of `cc` is included with it, but `sh` executing `tee` isn't a descendant of `sh`
+    assert_eq!(
+        filter(&["--only-exe", "sh", "--descendants"]),
+        [&[r#""/bin/sh""#][..], &cc].concat()
+    );
+    assert_eq!(
+        filter(&["--exclude-exe", "sh", "--descendants"]),
+        vec![r#""/usr/bin/tee""#, r#""/src/log""#]
+    );
+}
+
+#[test]
+fn replay_path_filters() {
+    assert_eq!(
+        lines(&ftrace(&[
+            "--input",
+            FIXTURE,
+            "--exclude-set",
+            "system",
+            "-u"
+        ])),
+        vec![
+            r#""/usr/bin/ls""#,
+            r#""/lib/x86_64-linux-gnu/libc.so.6""#,
+            r#""/""#,
+        ]
+    );
+    assert_eq!(
+        lines(&ftrace(&[
+            "--input",
+            FIXTURE,
+            "--include",
+            "/lib/**",
+            "--regex",
+            "^/etc/"
+        ])),
+        vec![
+            r#""/etc/ld.so.cache""#,
+            r#""/lib/x86_64-linux-gnu/libc.so.6""#,
+            r#""/etc/ld.so.cache""#,
+        ]
+    );
+    assert_eq!(
+        lines(&ftrace(&[
+            "--input",
+            FIXTURE,
+            "--exclude",
+            "*.so*",
+            "--exclude",
+            "/"
+        ])),
+        vec![r#""/usr/bin/ls""#]
+    );
+}
+
+#[test]
+fn replay_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let (xdg, project) = (dir.path().join("xdg"), dir.path().join("project"));
+    fs::create_dir_all(xdg.join("ftrace")).unwrap();
+    fs::create_dir_all(project.join("sub")).unwrap();
+    fs::write(
+        xdg.join("ftrace/config.toml"),
+        "show-mode = true\nexclude-set = [\"system\"]\n[profile.libs]\ninclude = [\"/usr/**\"]\n",
+    )
+    .unwrap();
+    fs::write(
+        project.join(".ftrace.toml"),
+        "no-duplicates = true\n[profile.libs]\ninclude = [\"/lib/**\"]\n",
+    )
+    .unwrap();
+
+    let ftrace = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_ftrace"))
+            .args(["--input", FIXTURE])
+            .args(args)
+            .env("XDG_CONFIG_HOME", &xdg)
+            .current_dir(project.join("sub"))
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap(),
+        )
+    };
+
+    assert_eq!(
+        ftrace(&[]),
+        (
+            Some(0),
+            "r  \"/usr/bin/ls\"\nr  \"/lib/x86_64-linux-gnu/libc.so.6\"\nr  \"/\"\n".to_string()
+        )
+    );
+    // the project's profile replaces the user's, and options on the command line replace both
+    assert_eq!(
+        ftrace(&["--profile", "libs"]),
+        (
+            Some(0),
+            "r  \"/lib/x86_64-linux-gnu/libc.so.6\"\n".to_string()
+        )
+    );
+    assert_eq!(
+        ftrace(&["--profile", "libs", "--include", "/usr/bin/*"]),
+        (Some(0), "r  \"/usr/bin/ls\"\n".to_string())
+    );
+    assert_eq!(ftrace(&["--profile", "missing"]).0, Some(2));
+}
+
+#[test]
+fn replay_depfile() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in &[
+        "main.c",
+        "my file.h",
+        "cost$.h",
+        "x:y.h",
+        "a\\ b#.h",
+        "main.o",
+    ] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    fs::create_dir(dir.path().join("include")).unwrap();
+
+    let fixture = hex_fixture(
+        dir.path(),
+        &[
+            r#"openat(AT_FDCWD, "main.c", O_RDONLY) = 3"#,
+            r#"openat(AT_FDCWD, "my file.h", O_RDONLY) = 3"#,
+            r#"openat(AT_FDCWD, "cost$.h", O_RDONLY) = 3"#,
+            r#"openat(AT_FDCWD, "x:y.h", O_RDONLY) = 3"#,
+            r#"openat(AT_FDCWD, "a\ b#.h", O_RDONLY) = 3"#,
+            r#"openat(AT_FDCWD, "missing.h", O_RDONLY) = -1 ENOENT (No such file or directory)"#,
+            r#"openat(AT_FDCWD, "include", O_RDONLY|O_DIRECTORY) = 3"#,
+            r#"openat(AT_FDCWD, "main.o", O_WRONLY|O_CREAT|O_TRUNC, 0666) = 4"#,
+            r#"openat(AT_FDCWD, "main.o", O_RDONLY) = 4"#,
+        ],
+    );
+
+    let output = dir.path().join("main.d");
+    let dir = dir.path().to_str().unwrap();
+    let depfile = |args: &[&str]| {
+        let stdout = ftrace(
+            &[
+                &[
+                    "--input",
+                    fixture.to_str().unwrap(),
+                    "--cwd",
+                    dir,
+                    "--format",
+                    "depfile",
+                    "--output",
+                    output.to_str().unwrap(),
+                ],
+                args,
+            ]
+            .concat(),
+        );
+        // nothing but the traced program's own output should be printed
+        assert_eq!(stdout, "");
+        fs::read_to_string(&output).unwrap().replace(dir, "DIR")
+    };
+    assert_eq!(
+        depfile(&["--target", "main o"]),
+        "main\\ o: \\\n  DIR/main.c \\\n  DIR/my\\ file.h \\\n  DIR/cost$$.h \\\n  DIR/x\\:y.h \\\n  DIR/a\\\\\\ b\\#.h\n"
+    );
+    assert_eq!(
+        depfile(&["--written-targets"]),
+        "DIR/main.o: \\\n  DIR/main.c \\\n  DIR/my\\ file.h \\\n  DIR/cost$$.h \\\n  DIR/x\\:y.h \\\n  DIR/a\\\\\\ b\\#.h\n"
+    );
+
+    // the rule would have no targets
+    let output = ftrace_status(&[
+        "--input",
+        FIXTURE,
+        "--format",
+        "depfile",
+        "--written-targets",
+        "--output",
+        output.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+
+    let output = ftrace_status(&["--input", FIXTURE, "--format", "depfile"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = ftrace_status(&["--input", FIXTURE, "--format", "depfile", "--target", "a"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn replay_apparmor() {
+    const SERVICE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/service.strace");
+    let dir = tempfile::tempdir().unwrap();
+    let profile = dir.path().join("usr.bin.python3");
+    let output = ftrace(&[
+        "--input",
+        SERVICE,
+        "--format",
+        "apparmor",
+        "--transition",
+        "sendmail",
+        "--output",
+        profile.to_str().unwrap(),
+    ]);
+    assert_eq!(output, "");
+    assert_eq!(
+        fs::read_to_string(&profile).unwrap(),
+        r#"# Generated by ftrace from: python3 app.py
+#include <tunables/global>
+
+profile python3 /usr/bin/python3 {
+  #include <abstractions/base>
+
+  /lib/x86_64-linux-gnu/libc.so.6 rm,
+  /proc/@{pid}/status r,
+  "/srv/data \[1\].db" rw,
+  /usr/bin/git ix,
+  /usr/lib/python3/* r,
+  /usr/sbin/sendmail px,
+}
+"#
+    );
+
+    let output = ftrace_status(&["--input", SERVICE, "--format", "apparmor"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = ftrace_status(&[
+        "--input",
+        SERVICE,
+        "--format",
+        "apparmor",
+        "-o",
+        profile.to_str().unwrap(),
+        "--relative-to",
+        "/usr",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn replay_record() {
+    let dir = tempfile::tempdir().unwrap();
+    let tool = dir.path().join("tool");
+    fs::write(&tool, "#!/nonexistent/interpreter\n").unwrap();
+
+    let fixture = hex_fixture(
+        dir.path(),
+        &[
+            format!(
+                r#"execve("{}", ["tool"], 0x7ffc3b1d4c28 /* 24 vars */) = 0"#,
+                tool.display()
+            ),
+            r#"openat(AT_FDCWD, "/src/data.txt", O_RDONLY) = 3"#.to_string(),
+            r#"openat(AT_FDCWD, "/src/db", O_RDWR) = 3"#.to_string(),
+            r#"openat(AT_FDCWD, "/src/out.txt", O_WRONLY|O_CREAT|O_TRUNC, 0666) = 4"#.to_string(),
+            r#"openat(AT_FDCWD, "/src/missing", O_RDONLY) = -1 ENOENT (No such file or directory)"#
+                .to_string(),
+        ],
+    );
+
+    let policy = dir.path().join("policy.toml");
+    let output = ftrace(&[
+        "--input",
+        fixture.to_str().unwrap(),
+        "record",
+        "--policy",
+        policy.to_str().unwrap(),
+    ]);
+    assert_eq!(output, "");
+    assert_eq!(
+        fs::read_to_string(&policy).unwrap(),
+        format!(
+            "# Recorded by ftrace\nread = [\n    '/src/data.txt',\n    '/src/db',\n    '{0}',\n]\nwrite = [\n    '/src/db',\n    '/src/out.txt',\n]\nexecute = [\n    '/nonexistent/interpreter',\n    '{0}',\n]\n",
+            tool.display()
+        )
+    );
+
+    // the filters of the printed paths don't apply, since the policy has to allow every access
+    let filtered = dir.path().join("filtered.toml");
+    ftrace(&[
+        "--input",
+        fixture.to_str().unwrap(),
+        "--type",
+        "d",
+        "--exclude",
+        "/src/**",
+        "--relative-to",
+        "/tmp",
+        "record",
+        "--policy",
+        filtered.to_str().unwrap(),
+    ]);
+    assert_eq!(
+        fs::read_to_string(&filtered).unwrap(),
+        fs::read_to_string(&policy).unwrap()
+    );
+}
+
+#[test]
+fn replay_non_utf8_paths() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let name = OsStr::from_bytes(b"caf\xe9 it's");
+    fs::write(dir.path().join(name), "").unwrap();
+
+    let path = dir.path().join(name);
+    let call = [
+        &b"openat(AT_FDCWD, \""[..],
+        path.as_os_str().as_bytes(),
+        b"\", O_RDONLY) = 3",
+    ]
+    .concat();
+    let fixture = hex_fixture(dir.path(), &[&call, &call]);
+
+    let output = |quote: &str| {
+        let output = ftrace_status(&[
+            "--input",
+            fixture.to_str().unwrap(),
+            "--type",
+            "f",
+            "--no-duplicates",
+            "--quote",
+            quote,
+        ]);
+        assert!(output.status.success(), "{:?}", output);
+        output.stdout
+    };
+    let dir = dir.path().to_str().unwrap();
+    assert_eq!(
+        output("c"),
+        format!("\"{}/caf\\xe9 it's\"\n", dir).as_bytes()
+    );
+    assert_eq!(
+        output("shell"),
+        format!("$'{}/caf\\xe9 it\\'s'\n", dir).as_bytes()
+    );
+    assert_eq!(
+        output("none"),
+        [path.as_os_str().as_bytes(), b"\n"].concat()
+    );
+    assert_eq!(
+        output("json"),
+        format!("\"{}/caf\u{fffd} it's\"\n", dir).as_bytes()
+    );
+}
+
+#[test]
+fn replay_plain_and_print0() {
+    assert_eq!(
+        ftrace(&["--input", FIXTURE, "--plain", "--no-duplicates"]),
+        "/usr/bin/ls\n/etc/ld.so.cache\n/lib/x86_64-linux-gnu/libc.so.6\n/\n"
+    );
+    // colors are never printed, since they'd corrupt the paths
+    assert_eq!(
+        ftrace(&["--input", FIXTURE, "--print0", "--no-duplicates", "--color"]),
+        "/usr/bin/ls\0/etc/ld.so.cache\0/lib/x86_64-linux-gnu/libc.so.6\0/\0"
+    );
+
+    let output = ftrace_status(&["--input", FIXTURE, "--print0", "--show-mode"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn replay_archive() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = fs::canonicalize(dir.path()).unwrap();
+    fs::create_dir(root.join("data")).unwrap();
+    fs::write(root.join("data/real.txt"), "contents").unwrap();
+    fs::set_permissions(
+        root.join("data/real.txt"),
+        fs::Permissions::from_mode(0o640),
+    )
+    .unwrap();
+    fs::write(root.join("out.txt"), "").unwrap();
+    symlink("data", root.join("lib")).unwrap();
+    symlink("lib/real.txt", root.join("alias.txt")).unwrap();
+
+    let path = |name: &str| root.join(name).display().to_string();
+    let fixture = hex_fixture(
+        &root,
+        &[
+            format!(r#"openat(AT_FDCWD, "{}", O_RDONLY) = 3"#, path("alias.txt")),
+            format!(
+                r#"openat(AT_FDCWD, "{}", O_RDONLY) = -1 ENOENT (No such file or directory)"#,
+                path("missing.txt")
+            ),
+            format!(
+                r#"openat(AT_FDCWD, "{}", O_WRONLY|O_CREAT|O_TRUNC, 0666) = 4"#,
+                path("out.txt")
+            ),
+        ],
+    );
+
+    let archive = root.join("app.tar");
+    let output = ftrace(&[
+        "--input",
+        fixture.to_str().unwrap(),
+        "--archive",
+        archive.to_str().unwrap(),
+    ]);
+    // paths that didn't exist are only archived, not printed
+    assert_eq!(
+        lines(&output),
+        vec![
+            format!("{:?}", root.join("alias.txt")),
+            format!("{:?}", root.join("out.txt")),
+        ]
+    );
+
+    let name = |name: &str| root.join(name).strip_prefix("/").unwrap().to_path_buf();
+    let mut entries = vec![];
+    let mut tar = tar::Archive::new(fs::File::open(&archive).unwrap());
+    for entry in tar.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let mut contents = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut contents).unwrap();
+        entries.push((
+            entry.path().unwrap().into_owned(),
+            entry.link_name().unwrap().map(|link| link.into_owned()),
+            entry.header().mode().unwrap(),
+            contents,
+        ));
+    }
+    assert_eq!(
+        entries,
+        vec![
+            (
+                name("alias.txt"),
+                Some("lib/real.txt".into()),
+                0o777,
+                String::new()
+            ),
+            (name("lib"), Some("data".into()), 0o777, String::new()),
+            (name("data/real.txt"), None, 0o640, "contents".into()),
+            (
+                ".ftrace/missing".into(),
+                None,
+                0o644,
+                format!("{:?}\n", root.join("missing.txt"))
+            ),
+        ]
+    );
+}
+
+#[test]
+fn replay_hash() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::write(root.join("input.txt"), "input").unwrap();
+    fs::write(root.join("back\\slash.txt"), "escaped").unwrap();
+    fs::write(root.join("output.txt"), "output").unwrap();
+
+    let path = |name: &str| root.join(name).display().to_string();
+    let fixture = hex_fixture(
+        root,
+        &[
+            format!(
+                r#"[pid 10] openat(AT_FDCWD, "{}", O_RDONLY) = 3"#,
+                path("input.txt")
+            ),
+            format!(
+                r#"[pid 10] openat(AT_FDCWD, "{}", O_RDONLY) = 3"#,
+                path("back\\slash.txt")
+            ),
+            format!(
+                r#"[pid 10] openat(AT_FDCWD, "{}", O_WRONLY|O_CREAT|O_TRUNC, 0666) = 4"#,
+                path("output.txt")
+            ),
+            r#"[pid 10] openat(AT_FDCWD, "/", O_RDONLY|O_DIRECTORY) = 5"#.to_string(),
+            "[pid 10] +++ exited with 0 +++".to_string(),
+        ],
+    );
+
+    let manifest = format!(
+        "\\044c5f4a04d6114914bde9e6ef5e5c8001e5b15101114d235aa61cdde7c6d718  {0}/back\\\\slash.txt\n\
+         c96c6d5be8d08a12e7b5cdc1b207fa6b2430974c86803d8891675e76fd992c20  {0}/input.txt\n\
+         e0ee8bb50685e05fa0f47ed04203ae953fdfd055f5bd2892ea186504254f8c3a  {0}/output.txt\n",
+        root.display()
+    );
+    let path = root.join("app.sha256");
+    let output = ftrace(&[
+        "--input",
+        fixture.to_str().unwrap(),
+        "--hash",
+        "sha256",
+        "--manifest",
+        path.to_str().unwrap(),
+    ]);
+    // the manifest is only written to the file, so it can't be mixed up with the paths
+    assert_eq!(
+        output,
+        format!(
+            "{:?}\n{:?}\n{:?}\n\"/\"\n",
+            root.join("input.txt"),
+            root.join("back\\slash.txt"),
+            root.join("output.txt"),
+        )
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), manifest);
+
+    let output = ftrace_status(&["--input", fixture.to_str().unwrap(), "--hash", "sha256"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn replay_diff() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let a = hex_fixture(
+        dir.path(),
+        &[
+            r#"openat(AT_FDCWD, "/etc/app.conf", O_RDONLY) = 3"#,
+            r#"openat(AT_FDCWD, "/usr/lib/libdep.so.1", O_RDONLY) = 3"#,
+            r#"openat(AT_FDCWD, "/etc/app.d", O_RDONLY) = -1 ENOENT (No such file or directory)"#,
+            r#"openat(AT_FDCWD, "/proc/100/maps", O_RDONLY) = 3"#,
+            r#"openat(AT_FDCWD, "/tmp/tmp.a8Zk3lQ2/cache", O_WRONLY|O_CREAT|O_TRUNC, 0666) = 4"#,
+            r#"openat(AT_FDCWD, "/var/log/app-2021-01-29T10:31:13.log", O_WRONLY|O_CREAT|O_APPEND, 0666) = 5"#,
+        ],
+    );
+
+    // the jsonl format has more fields, but only these are compared
+    let b = dir.path().join("b.jsonl");
+    let record = |path: &str, mode: &str, success: bool| {
+        format!(
+            r#"{{"path_bytes":"{}","mode":"{}","success":{}}}"#,
+            path.bytes()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>(),
+            mode,
+            success
+        )
+    };
+    let records = [
+        record("/etc/app.conf", "rw", true),
+        record("/usr/lib/libdep.so.2", "r", true),
+        record("/etc/app.d", "r", true),
+        record("/proc/200/maps", "r", true),
+        // lines that aren't JSON (e.g.: the program's own output) are ignored
+        "hello".to_string(),
+        record("/tmp/tmp.Qw3rTy9x/cache", "w", true),
+        record("/var/log/app-2021-02-01T08:00:00.log", "w", true),
+    ];
+    fs::write(&b, records.join("\n") + "\n").unwrap();
+
+    let diff = |args: &[&str]| {
+        let args = [&["diff"], args, &[a.to_str().unwrap(), b.to_str().unwrap()]].concat();
+        let output = ftrace_status(&args);
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap(),
+        )
+    };
+    assert_eq!(
+        diff(&[]),
+        (
+            Some(1),
+            [
+                r#"~ "/etc/app.conf" (r -> rw)"#,
+                r#"~ "/etc/app.d" (missing -> found)"#,
+                r#"- "/proc/100/maps" (r)"#,
+                r#"+ "/proc/200/maps" (r)"#,
+                r#"+ "/tmp/tmp.Qw3rTy9x/cache" (w)"#,
+                r#"- "/tmp/tmp.a8Zk3lQ2/cache" (w)"#,
+                r#"- "/usr/lib/libdep.so.1" (r)"#,
+                r#"+ "/usr/lib/libdep.so.2" (r)"#,
+                r#"- "/var/log/app-2021-01-29T10:31:13.log" (w)"#,
+                r#"+ "/var/log/app-2021-02-01T08:00:00.log" (w)"#,
+                "",
+            ]
+            .join("\n")
+        )
+    );
+    assert_eq!(
+        diff(&["--normalize", "pids,temp-dirs"]),
+        (
+            Some(1),
+            [
+                r#"~ "/etc/app.conf" (r -> rw)"#,
+                r#"~ "/etc/app.d" (missing -> found)"#,
+                r#"- "/usr/lib/libdep.so.1" (r)"#,
+                r#"+ "/usr/lib/libdep.so.2" (r)"#,
+                r#"- "/var/log/app-2021-01-29T10:31:13.log" (w)"#,
+                r#"+ "/var/log/app-2021-02-01T08:00:00.log" (w)"#,
+                "",
+            ]
+            .join("\n")
+        )
+    );
+
+    let output = ftrace_status(&[
+        "--include",
+        "/proc/**",
+        "--include",
+        "/var/**",
+        "diff",
+        "--normalize",
+        "all",
+        a.to_str().unwrap(),
+        b.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    assert_eq!(output.stdout, b"");
+}
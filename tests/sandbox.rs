@@ -0,0 +1,64 @@
+use std::fs;
+use std::process::{Command, Output};
+
+fn ftrace(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ftrace"))
+        .args(args)
+        // ignore the user's config file
+        .env("XDG_CONFIG_HOME", "/nonexistent")
+        .output()
+        .unwrap()
+}
+
+/// Whether programs can be traced with the ptrace backend and sandboxed with Landlock, which depends on the kernel and
+/// on any seccomp filter of the container that the tests run in.
+fn is_supported() -> bool {
+    if let Err(e) = ftrace::landlock::abi() {
+        eprintln!("skipping, Landlock isn't supported: {}", e);
+        return false;
+    }
+    let output = ftrace(&["--backend", "ptrace", "--", "true"]);
+    if !output.status.success() {
+        eprintln!(
+            "skipping, the ptrace backend isn't supported: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return false;
+    }
+
+    true
+}
+
+#[test]
+fn enforce() {
+    if !is_supported() {
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let (allowed, denied) = (dir.path().join("allowed"), dir.path().join("denied"));
+    fs::create_dir(&allowed).unwrap();
+    fs::create_dir(&denied).unwrap();
+    fs::write(allowed.join("a.txt"), "a\n").unwrap();
+    fs::write(denied.join("b.txt"), "b\n").unwrap();
+    let (a, b) = (allowed.join("a.txt"), denied.join("b.txt"));
+    let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+    let policy = dir.path().join("policy.toml");
+    let policy = policy.to_str().unwrap();
+    let output = ftrace(&["record", "--policy", policy, "--", "cat", a]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a\n");
+
+    // the recorded run is allowed
+    let output = ftrace(&["enforce", "--policy", policy, "--", "cat", a]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a\n");
+
+    // reading a file that wasn't recorded is denied and printed, and the exit code of the program is kept
+    let output = ftrace(&["enforce", "--policy", policy, "--", "cat", a, b]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("a\n"), "{}", stdout);
+    assert!(stdout.contains(&format!("\"{}\"", b)), "{}", stdout);
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+
+use ftrace::analysis::AccessMode;
+use ftrace::backend::{strace, Backend};
+use ftrace::filter::AccessModes;
+use ftrace::parse::CallResult;
+use ftrace::{Event, ExitStatus, FileAccess, Tracer};
+
+const MAKE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/make.strace");
+
+fn accesses(tracer: Tracer) -> Vec<FileAccess> {
+    let mut trace = tracer.spawn().unwrap();
+    let accesses = (&mut trace)
+        .filter_map(|event| match event {
+            Event::Access(access) => Some(access),
+            _ => None,
+        })
+        .collect();
+
+    trace.wait().unwrap();
+    accesses
+}
+
+#[test]
+fn input() {
+    let tracer = Tracer::input(BufReader::new(File::open(MAKE).unwrap()))
+        .cwd("/src")
+        .access_modes(AccessModes {
+            write: true,
+            ..AccessModes::default()
+        });
+
+    assert_eq!(
+        accesses(tracer),
+        vec![FileAccess {
+            path: PathBuf::from("/src/sub/out.txt"),
+            syscall: "openat".to_string(),
+            pid: Some(4242),
+            time: Some(std::time::Duration::from_micros(1611916400000700)),
+            result: CallResult::Int(4),
+            info: None,
+            duration: None,
+            success: Some(true),
+            mode: AccessMode::Write,
+        }]
+    );
+}
+
+#[test]
+fn input_relative_to() {
+    let tracer = Tracer::input(BufReader::new(File::open(MAKE).unwrap()))
+        .cwd("/src")
+        .relative_to("/src/lib");
+
+    assert_eq!(
+        accesses(tracer)
+            .into_iter()
+            .map(|access| access.path)
+            .collect::<Vec<_>>(),
+        vec![
+            PathBuf::from("../../usr/bin/make"),
+            PathBuf::from("../sub"),
+            PathBuf::from("../sub/Makefile"),
+            PathBuf::from("."),
+            PathBuf::from("lib.c"),
+            PathBuf::from("../sub/out.txt"),
+        ]
+    );
+}
+
+#[test]
+fn exit_status() {
+    let exit = |tracer: Tracer| {
+        let mut trace = tracer.spawn().unwrap();
+        (&mut trace).for_each(drop);
+        trace.wait().unwrap()
+    };
+    let input = |trace: &'static str| Tracer::input(Cursor::new(trace));
+
+    assert_eq!(
+        exit(Tracer::input(BufReader::new(File::open(MAKE).unwrap()))),
+        Some(ExitStatus::Code(0))
+    );
+    // only the exit of the initial process is used
+    assert_eq!(
+        exit(input(
+            "close(3) = 0\n[pid 7] +++ exited with 1 +++\n+++ killed by SIGSEGV +++\n"
+        )),
+        Some(ExitStatus::Signal(11))
+    );
+    assert_eq!(ExitStatus::Signal(11).code(), 139);
+    assert_eq!(
+        exit(input("4242 close(3) = 0\n4243 +++ exited with 1 +++\n")),
+        None
+    );
+}
+
+#[test]
+fn empty_command() {
+    for backend in [Backend::Ptrace, Backend::Strace] {
+        let error = Tracer::command(Vec::<String>::new())
+            .backend(backend)
+            .spawn()
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "No command was given to trace");
+    }
+}
+
+/// The successful accesses made by `cat` with the given backend, or `None` if the backend can't trace it.
+fn cat_accesses(backend: Backend, path: &Path) -> Option<BTreeSet<(PathBuf, String, String)>> {
+    let mut trace = match Tracer::command(["cat", path.to_str().unwrap()])
+        .backend(backend)
+        .spawn()
+    {
+        Ok(trace) => trace,
+        Err(e) => {
+            eprintln!("skipping, the {:?} backend isn't supported: {}", backend, e);
+            return None;
+        }
+    };
+    let accesses = (&mut trace)
+        .filter_map(|event| match event {
+            Event::Access(access) if access.success == Some(true) => {
+                Some((access.path, access.syscall, format!("{:?}", access.mode)))
+            }
+            _ => None,
+        })
+        .collect();
+
+    match trace.wait() {
+        Ok(Some(ExitStatus::Code(0))) => Some(accesses),
+        status => {
+            eprintln!("skipping, the {:?} backend failed: {:?}", backend, status);
+            None
+        }
+    }
+}
+
+#[test]
+fn backends_match() {
+    // the file is empty so `cat` doesn't print anything
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("empty.txt");
+    fs::write(&path, "").unwrap();
+
+    let ptrace = match cat_accesses(Backend::Ptrace, &path) {
+        Some(accesses) => accesses,
+        None => return,
+    };
+    // accesses that are only made through a file descriptor are seen
+    assert!(
+        ptrace.contains(&(path.clone(), "read".to_string(), "Read".to_string())),
+        "{:?}",
+        ptrace
+    );
+    assert!(
+        ptrace.iter().any(|(_, syscall, _)| syscall == "mmap"),
+        "{:?}",
+        ptrace
+    );
+
+    if strace::find().is_err() {
+        eprintln!("skipping, strace isn't installed");
+        return;
+    }
+    if let Some(strace) = cat_accesses(Backend::Strace, &path) {
+        assert_eq!(ptrace, strace);
+    }
+}